@@ -0,0 +1,56 @@
+//! in_session查表与逐个时段比较的基准测试, 运行: cargo bench -p tradesession
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use tradesession::TradeSession;
+use tradesession::jcswitch::*;
+
+/// 一天中每隔7秒一个tick, 共ROUNDS轮
+const ROUNDS: usize = 200;
+
+fn bench(name: &str, ticks: &[MyTimeType], f: impl Fn(&MyTimeType) -> bool) {
+    let start = Instant::now();
+    let mut count = 0usize;
+    for _ in 0..ROUNDS {
+        for ts in ticks {
+            count += f(black_box(ts)) as usize;
+        }
+    }
+    let elapsed = start.elapsed();
+    let calls = ROUNDS * ticks.len();
+    println!(
+        "{name:<40} {:>8.2} ns/call ({count} hits in {calls} calls)",
+        elapsed.as_nanos() as f64 / calls as f64
+    );
+}
+
+fn main() {
+    let ticks: Vec<MyTimeType> = (0..86400u32)
+        .step_by(7)
+        .map(|sec| make_time(sec / 3600, sec / 60 % 60, sec % 60))
+        .collect();
+    let sessions = [
+        (
+            "commodity_night",
+            TradeSession::new_commodity_session_night(),
+        ),
+        ("stock_index", TradeSession::new_stock_index_session()),
+        ("full", TradeSession::new_full_session()),
+        // 隔一分钟开市一分钟, 共720个时段
+        (
+            "every_other_minute",
+            TradeSession::new_from_minutes((0..1440u16).step_by(2)),
+        ),
+    ];
+    for (name, session) in &sessions {
+        for (begin, end) in [(true, false), (false, true)] {
+            bench(&format!("{name} scan ({begin}, {end})"), &ticks, |ts| {
+                session.in_session_scan(ts, begin, end)
+            });
+            bench(&format!("{name} bitmap ({begin}, {end})"), &ticks, |ts| {
+                session.in_session(ts, begin, end)
+            });
+        }
+    }
+}
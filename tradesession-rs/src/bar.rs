@@ -0,0 +1,441 @@
+use crate::jcswitch::*;
+use crate::tradesession::{SessionSlice, ShiftedTime, TradeSession};
+
+/// 多分钟k线的对齐方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BarAlign {
+    /// 从夜盘开盘及日盘开盘起累计交易分钟数, bar可以跨越盘中休息, 夜盘21:00及日盘开盘时重新开始,
+    /// 比如商品期货60分钟bar在10:00, 11:15, 14:15, 15:00结束
+    #[default]
+    SessionStart,
+    /// 按时钟整点对齐, bar不跨越盘中休息,
+    /// 比如商品期货60分钟bar在10:00, 10:15, 11:00, 11:30, 14:00, 15:00结束
+    Clock,
+}
+
+/// 一根k线的起止时间, 所有时间平移到以anchor为0点, 默认增加4小时, 参看ShiftedTime
+/// 切分k线时使用左开右闭区间(begin, end], 整点时间属于前一根bar,
+/// 但每个时段的第一根bar含开始, 即[begin, end], 比如[9:00:00～9:01:00], [10:30:00~10:31:00]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BarSpan {
+    begin: ShiftedTime,
+    end: ShiftedTime,
+    include_begin: bool,
+    /// 交易日开始时间, 自零点起的秒数
+    anchor: u32,
+}
+
+impl BarSpan {
+    /// 注意：超前4小时
+    pub fn begin(&self) -> ShiftedTime {
+        self.begin
+    }
+    /// 注意：超前4小时
+    pub fn end(&self) -> ShiftedTime {
+        self.end
+    }
+    /// 是否含开始, 时段的第一根bar含开始
+    pub fn include_begin(&self) -> bool {
+        self.include_begin
+    }
+    /// 开始时间, 实际时间
+    pub fn begin_time(&self) -> MyTimeType {
+        self.begin.nominal_time_anchored(self.anchor)
+    }
+    /// 结束时间, 实际时间
+    pub fn end_time(&self) -> MyTimeType {
+        self.end.nominal_time_anchored(self.anchor)
+    }
+
+    /// 一个时间点, 属于这根bar吗? 纳秒数非零时归于后一秒, 参看ShiftedTime
+    pub fn contains(&self, ts: &MyTimeType) -> bool {
+        let sec = ShiftedTime::from_time_anchored(ts, self.anchor);
+        if self.include_begin {
+            sec >= self.begin && sec <= self.end
+        } else {
+            sec > self.begin && sec <= self.end
+        }
+    }
+}
+
+impl TradeSession {
+    /// 一个交易日的所有1分钟bar, 按时间排序,
+    /// with_auction为true时, 把开盘集合竞价的撮合阶段并入紧接着的第一根bar,
+    /// 比如[8:59:00～9:01:00], 没有集合竞价时段时不合并
+    pub fn minute_bars(&self, with_auction: bool) -> Vec<BarSpan> {
+        let mut bars = vec![];
+        for slice in self.get_slices() {
+            let end = slice.end().seconds();
+            let mut begin = slice.begin().seconds();
+            let mut include_begin = true;
+            while begin < end {
+                // 时段不是整分钟开始时(比如08:59:30), bar仍在整分钟结束
+                let bar_end = ((begin / 60 + 1) * 60).min(end);
+                bars.push(BarSpan {
+                    begin: ShiftedTime(begin),
+                    end: ShiftedTime(bar_end),
+                    include_begin,
+                    anchor: slice.anchor(),
+                });
+                include_begin = false;
+                begin = bar_end;
+            }
+        }
+        if with_auction {
+            self.fold_auctions(&mut bars);
+        }
+        bars
+    }
+
+    /// 一个交易日的所有period分钟bar, 按时间排序, 比如3/5/15/30/60分钟及2小时(120)/4小时(240),
+    /// 收盘及夜盘收盘时总是结束一根bar, 对齐方式参看BarAlign, with_auction参看minute_bars,
+    /// period为0时返回空数组
+    pub fn bar_boundaries(&self, period: u32, align: BarAlign, with_auction: bool) -> Vec<BarSpan> {
+        if period == 0 {
+            return vec![];
+        }
+        let minutes = self.minute_bars(false);
+        let night_end = self.night_end();
+        let mut bars: Vec<BarSpan> = vec![];
+        let mut count = 0;
+        for (idx, minute) in minutes.iter().enumerate() {
+            let is_night = minute.begin.seconds() < night_end;
+            // 夜盘开盘或日盘开盘, 重新开始累计
+            let part_begin = idx == 0 || (minutes[idx - 1].begin.seconds() < night_end) != is_night;
+            let new_bar = match align {
+                BarAlign::SessionStart => part_begin || count % period == 0,
+                BarAlign::Clock => {
+                    let nominal = minute.begin.nominal_seconds_anchored(minute.anchor) / 60;
+                    minute.include_begin || nominal % period == 0
+                }
+            };
+            if part_begin {
+                count = 0;
+            }
+            match bars.last_mut() {
+                Some(bar) if !new_bar => bar.end = minute.end,
+                _ => bars.push(*minute),
+            }
+            count += 1;
+        }
+        if with_auction {
+            self.fold_auctions(&mut bars);
+        }
+        bars
+    }
+
+    /// 一个交易日period分钟bar的个数, 可用于按bar_index分配数组
+    pub fn bar_count(&self, period: u32, align: BarAlign) -> usize {
+        self.bar_boundaries(period, align, false).len()
+    }
+
+    /// 一个时间点属于交易日的第几根period分钟bar(从0开始, 夜盘在前),
+    /// 开盘集合竞价的撮合阶段归入第一根bar, 纳秒数非零时归于后一秒,
+    /// 不在时段内(含盘中休息, 即使bar跨越休息)时返回None, 按时段计算, 不生成bar_boundaries
+    pub fn bar_index(&self, ts: &MyTimeType, period: u32, align: BarAlign) -> Option<u32> {
+        if period == 0 {
+            return None;
+        }
+        let sec = self.shift(ts).seconds();
+        let slices = self.get_slices();
+        // sec所在的时段, 及其在该时段中属于第几根1分钟bar, 同minute_bars,
+        // 同fold_auctions, 在时段开始的开盘集合竞价[match_begin, end)中时归入第一根
+        let (pos, minute) = slices.iter().enumerate().find_map(|(pos, slice)| {
+            let (begin, end) = (slice.begin().seconds(), slice.end().seconds());
+            let in_auction = self.auctions().iter().any(|a| {
+                !a.is_closing()
+                    && a.end() == slice.begin()
+                    && a.match_begin().seconds() <= sec
+                    && sec < begin
+            });
+            if in_auction || sec == begin {
+                Some((pos, 0))
+            } else if begin < sec && sec <= end {
+                Some((pos, sec.div_ceil(60) - begin / 60 - 1))
+            } else {
+                None
+            }
+        })?;
+        let idx = match align {
+            BarAlign::SessionStart => {
+                let night_end = self.night_end();
+                let night: u32 = slices
+                    .iter()
+                    .map(|slice| night_minute_count(slice, night_end))
+                    .sum();
+                let nth = slices[..pos].iter().map(minute_count).sum::<u32>() + minute;
+                if nth < night {
+                    nth / period
+                } else {
+                    night.div_ceil(period) + (nth - night) / period
+                }
+            }
+            BarAlign::Clock => {
+                slices[..pos]
+                    .iter()
+                    .map(|slice| clock_bar_count(slice, period, minute_count(slice) - 1))
+                    .sum::<u32>()
+                    + clock_bar_count(&slices[pos], period, minute)
+                    - 1
+            }
+        };
+        Some(idx)
+    }
+
+    /// bar_index的逆运算, 第index根period分钟bar的结束时间, 超出范围时返回None
+    pub fn bar_end_time(&self, index: u32, period: u32, align: BarAlign) -> Option<MyTimeType> {
+        self.bar_boundaries(period, align, false)
+            .get(index as usize)
+            .map(|bar| bar.end_time())
+    }
+
+    /// 一个交易日的所有1分钟bar的结束时间, 实际时间, 按交易时间先后排序, 夜盘在前
+    pub fn minute_bar_ends(&self) -> Vec<MyTimeType> {
+        self.minute_bars(false)
+            .iter()
+            .map(|bar| bar.end_time())
+            .collect()
+    }
+
+    /// 开盘集合竞价的撮合阶段并入紧接着的第一根bar
+    fn fold_auctions(&self, bars: &mut [BarSpan]) {
+        for auction in self.auctions().iter().filter(|a| !a.is_closing()) {
+            let first = bars
+                .iter_mut()
+                .find(|bar| bar.include_begin && bar.begin == auction.end());
+            if let Some(bar) = first {
+                bar.begin = auction.match_begin();
+            }
+        }
+    }
+}
+
+/// 时段的1分钟bar个数, 开始或结束不是整分钟时包含不完整的分钟, 同minute_bars
+fn minute_count(slice: &SessionSlice) -> u32 {
+    slice.end().seconds().div_ceil(60) - slice.begin().seconds() / 60
+}
+
+/// 时段中开始时间早于night_end(即属于夜盘)的1分钟bar个数
+fn night_minute_count(slice: &SessionSlice, night_end: u32) -> u32 {
+    let begin = slice.begin().seconds();
+    if begin >= night_end {
+        return 0;
+    }
+    // 第一根从begin开始, 其余从整分钟开始
+    (1 + night_end.div_ceil(60).saturating_sub(begin / 60 + 1)).min(minute_count(slice))
+}
+
+/// Clock对齐时, 时段的第0~minute根1分钟bar中, 开始一根新的period分钟bar的个数,
+/// 第一根总是新bar, 其余在实际时间是period整数倍的分钟开始新bar
+fn clock_bar_count(slice: &SessionSlice, period: u32, minute: u32) -> u32 {
+    let first = slice.begin().seconds() / 60 + slice.anchor() / 60;
+    1 + aligned_minutes_below(first + minute + 1, period) - aligned_minutes_below(first + 1, period)
+}
+
+/// [0, n)中, 对一天的分钟数(1440)取余之后是period整数倍的个数
+fn aligned_minutes_below(n: u32, period: u32) -> u32 {
+    n / 1440 * 1440u32.div_ceil(period) + (n % 1440).div_ceil(period)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::phase::TradingPhase;
+
+    #[test]
+    fn minute_bars() {
+        let session = TradeSession::new_commodity_session_night();
+        let bars = session.minute_bars(false);
+        // 夜盘21:00~2:30共330分钟, 日盘225分钟
+        assert_eq!(bars.len(), 330 + 225);
+        assert_eq!(bars[0].begin_time(), make_time(21, 0, 0));
+        assert_eq!(bars[0].end_time(), make_time(21, 1, 0));
+        assert!(bars[0].contains(&make_time(21, 0, 0)));
+        assert!(bars[0].contains(&make_time(21, 1, 0)));
+        assert!(!bars[1].contains(&make_time(21, 1, 0)));
+        // 非零纳秒归于后一秒, 属于下一根bar
+        assert!(bars[1].contains(&time_from_midnight_nanos(
+            (21 * 3600 + 60) * 1_000_000_000 + 500_000_000
+        )));
+
+        let ends = session.minute_bar_ends();
+        assert_eq!(ends.last(), Some(&make_time(15, 0, 0)));
+        let idx = ends
+            .iter()
+            .position(|t| *t == make_time(10, 15, 0))
+            .unwrap();
+        assert_eq!(ends[idx + 1], make_time(10, 31, 0));
+        assert!(bars[idx + 1].include_begin());
+        assert!(bars[idx + 1].contains(&make_time(10, 30, 0)));
+
+        let day = TradeSession::new_commodity_session();
+        let bars = day.minute_bars(true);
+        assert_eq!(bars[0].begin_time(), make_time(8, 59, 0));
+        assert_eq!(bars[0].end_time(), make_time(9, 1, 0));
+        assert!(bars[0].contains(&make_time(8, 59, 30)));
+        assert_eq!(day.minute_bars(false)[0].begin_time(), make_time(9, 0, 0));
+    }
+
+    #[test]
+    fn bar_boundaries() {
+        let ends = |session: &TradeSession, period, align| -> Vec<MyTimeType> {
+            session
+                .bar_boundaries(period, align, false)
+                .iter()
+                .map(|bar| bar.end_time())
+                .collect()
+        };
+        let hm = |list: &[(u32, u32)]| -> Vec<MyTimeType> {
+            list.iter().map(|&(h, m)| make_time(h, m, 0)).collect()
+        };
+
+        let night = TradeSession::new_commodity_session_night();
+        assert_eq!(
+            ends(&night, 60, BarAlign::SessionStart),
+            hm(&[
+                (22, 0),
+                (23, 0),
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (2, 30),
+                (10, 0),
+                (11, 15),
+                (14, 15),
+                (15, 0)
+            ])
+        );
+        assert_eq!(
+            ends(&night, 60, BarAlign::Clock),
+            hm(&[
+                (22, 0),
+                (23, 0),
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (2, 30),
+                (10, 0),
+                (10, 15),
+                (11, 0),
+                (11, 30),
+                (14, 0),
+                (15, 0)
+            ])
+        );
+
+        let day = TradeSession::new_commodity_session();
+        assert_eq!(
+            ends(&day, 30, BarAlign::SessionStart),
+            hm(&[
+                (9, 30),
+                (10, 0),
+                (10, 45),
+                (11, 15),
+                (13, 45),
+                (14, 15),
+                (14, 45),
+                (15, 0)
+            ])
+        );
+        assert_eq!(ends(&day, 240, BarAlign::SessionStart), hm(&[(15, 0)]));
+        assert_eq!(
+            day.bar_boundaries(1, BarAlign::Clock, true),
+            day.minute_bars(true)
+        );
+        assert!(day.bar_boundaries(0, BarAlign::Clock, false).is_empty());
+
+        // 跨越休息的bar不含开始, 60分钟bar(10:00, 11:15]
+        let bar = night.bar_boundaries(60, BarAlign::SessionStart, false)[7];
+        assert!(!bar.include_begin());
+        assert!(bar.contains(&make_time(10, 30, 0)));
+        assert!(!bar.contains(&make_time(10, 0, 0)));
+    }
+
+    #[test]
+    fn bar_index() {
+        let session = TradeSession::new_commodity_session_night();
+        let align = BarAlign::SessionStart;
+        assert_eq!(session.bar_count(1, align), 555);
+        assert_eq!(session.bar_index(&make_time(20, 59, 30), 1, align), Some(0));
+        assert_eq!(session.bar_index(&make_time(21, 0, 0), 1, align), Some(0));
+        assert_eq!(session.bar_index(&make_time(21, 1, 0), 1, align), Some(0));
+        assert_eq!(session.bar_index(&make_time(21, 1, 1), 1, align), Some(1));
+        let nanos = (21 * 3600 + 60) * 1_000_000_000 + 1;
+        assert_eq!(
+            session.bar_index(&time_from_midnight_nanos(nanos), 1, align),
+            Some(1)
+        );
+        assert_eq!(session.bar_index(&make_time(10, 20, 0), 1, align), None);
+        assert_eq!(session.bar_index(&make_time(16, 0, 0), 1, align), None);
+
+        // 60分钟bar, 10:30在(10:00, 11:15]内
+        let idx = session.bar_index(&make_time(10, 30, 0), 60, align).unwrap();
+        assert_eq!(
+            session.bar_end_time(idx, 60, align),
+            Some(make_time(11, 15, 0))
+        );
+        assert_eq!(session.bar_end_time(10, 60, align), None);
+
+        for idx in 0..session.bar_count(5, BarAlign::Clock) as u32 {
+            let end = session.bar_end_time(idx, 5, BarAlign::Clock).unwrap();
+            assert_eq!(session.bar_index(&end, 5, BarAlign::Clock), Some(idx));
+        }
+
+        // 跨越休息的60分钟bar(10:00, 11:15], 休息时间不属于任何bar
+        assert_eq!(session.bar_index(&make_time(10, 20, 0), 60, align), None);
+        assert_eq!(session.bar_index(&make_time(10, 15, 0), 60, align), Some(7));
+        assert_eq!(session.bar_index(&make_time(10, 30, 0), 60, align), Some(7));
+        assert_eq!(session.bar_index(&make_time(10, 0, 0), 0, align), None);
+
+        // 股票09:25撮合之后的排队阶段同fold_auctions, 归入第一根bar
+        let stock = TradeSession::new_stock_session();
+        let first = stock.minute_bars(true)[0];
+        assert_eq!(first.begin_time(), make_time(9, 25, 0));
+        assert_eq!(stock.bar_index(&make_time(9, 27, 0), 1, align), Some(0));
+        assert_eq!(stock.bar_index(&make_time(9, 20, 0), 1, align), None);
+
+        // 与bar_boundaries一致
+        let mut anchored = TradeSession::new_with_anchor(&make_time(17, 0, 0));
+        anchored.add_slice(17, 0, 16, 0).unwrap();
+        anchored.post_fix();
+        let sessions = [
+            session,
+            TradeSession::new_commodity_session(),
+            TradeSession::new_stock_index_session(),
+            TradeSession::new_stock_session(),
+            anchored,
+        ];
+        for session in &sessions {
+            for (period, align) in [
+                (1, BarAlign::SessionStart),
+                (7, BarAlign::SessionStart),
+                (60, BarAlign::SessionStart),
+                (5, BarAlign::Clock),
+                (7, BarAlign::Clock),
+                (60, BarAlign::Clock),
+                (120, BarAlign::Clock),
+            ] {
+                let bars = session.bar_boundaries(period, align, true);
+                for secs in (0..86400).step_by(30) {
+                    let ts = time_from_midnight_nanos(secs * 1_000_000_000);
+                    let open = session.in_session(&ts, true, true)
+                        || matches!(
+                            session.phase_at(&ts),
+                            TradingPhase::AuctionMatch | TradingPhase::PreOpenQueue
+                        );
+                    let expected = bars
+                        .iter()
+                        .position(|bar| bar.contains(&ts))
+                        .filter(|_| open)
+                        .map(|idx| idx as u32);
+                    assert_eq!(
+                        session.bar_index(&ts, period, align),
+                        expected,
+                        "{period} {align:?} {ts}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! 检查交易时段csv文件中可疑的定义, 用法:
+//!
+//! ```text
+//! cargo run --bin session_lint -- tradesession.csv [more.csv ...]
+//! ```
+//!
+//! 没有问题时退出码为0, 有问题时为1, 文件读取失败时为2
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("usage: session_lint <tradesession.csv>...");
+        return ExitCode::from(2);
+    }
+    let mut found = false;
+    for path in &paths {
+        match tradesession::lint_csv(path) {
+            Ok(lints) => {
+                for lint in &lints {
+                    println!("{path}: {lint}");
+                }
+                found |= !lints.is_empty();
+            }
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                return ExitCode::from(2);
+            }
+        }
+    }
+    if found {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,66 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::tradesession::SECS_IN_ONE_DAY;
+
+/// 位图的字数, 共SECS_IN_ONE_DAY + 1位
+const WORDS: usize = (SECS_IN_ONE_DAY as usize + 1).div_ceil(64);
+
+/// 平移后一天中每一秒是否在时段内的位图, 第s + 1位表示[s, s + 1)在某个时段内, 第0位恒为0,
+/// 时段须已排序并合并(参看TradeSession::post_fix), 约10.8KB, clone时共享同一份数据
+#[derive(Clone, Default, PartialEq, Eq)]
+pub(crate) struct SecondBitmap {
+    /// 为空表示尚未生成或时段已被修改
+    words: Option<Arc<[u64]>>,
+}
+
+impl SecondBitmap {
+    /// ranges为平移之后的起止秒数[begin, end)
+    pub(crate) fn new(ranges: impl IntoIterator<Item = (u32, u32)>) -> Self {
+        let mut words = vec![0u64; WORDS];
+        for (begin, end) in ranges {
+            let end = end.min(SECS_IN_ONE_DAY);
+            for bit in (begin + 1)..(end + 1) {
+                words[bit as usize / 64] |= 1 << (bit % 64);
+            }
+        }
+        Self {
+            words: Some(words.into()),
+        }
+    }
+
+    /// 清除, 时段被修改之后调用
+    pub(crate) fn invalidate(&mut self) {
+        self.words = None;
+    }
+
+    /// 平移后的sec(小于SECS_IN_ONE_DAY)在时段内吗? 语义同TradeSession::in_session,
+    /// cur表示sec之后的一秒在时段内, prev表示sec之前的一秒在时段内,
+    /// 则含开始时cur即可, 含结束时prev即可, 都不含时须cur且prev(时段已合并, 所以是同一个时段),
+    /// 未生成时返回None
+    #[inline]
+    pub(crate) fn contains(
+        &self,
+        sec: u32,
+        include_begin: bool,
+        include_end: bool,
+    ) -> Option<bool> {
+        let words = self.words.as_deref()?;
+        let bit = |idx: u32| (words[idx as usize / 64] >> (idx % 64)) & 1 == 1;
+        let cur = bit(sec + 1);
+        let prev = bit(sec);
+        Some((cur & prev) | (cur & include_begin) | (prev & include_end))
+    }
+}
+
+impl Debug for SecondBitmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.words {
+            Some(words) => {
+                let seconds: u32 = words.iter().map(|w| w.count_ones()).sum();
+                write!(f, "SecondBitmap({seconds} seconds)")
+            }
+            None => write!(f, "SecondBitmap(None)"),
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use encoding_rs_io::DecodeReaderBytes;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::error::{Result, TradeSessionError};
+use crate::jcswitch::*;
+
+/// 适用于所有交易所的假日, csv文件中交易所列为空或者为"*"
+pub const ANY_EXCHANGE: &str = "*";
+
+/// 交易日历, 记录某个交易所的休市日期(节假日),
+/// 周六周日总是休市, 不需要列在假日中
+#[derive(Clone, Debug, Default)]
+pub struct TradingCalendar {
+    holidays: BTreeSet<MyDateType>,
+}
+
+impl TradingCalendar {
+    /// 空日历, 仅周末休市
+    pub fn new() -> Self {
+        Self {
+            holidays: BTreeSet::new(),
+        }
+    }
+
+    pub fn new_from_holidays<I>(holidays: I) -> Self
+    where
+        I: IntoIterator<Item = MyDateType>,
+    {
+        Self {
+            holidays: holidays.into_iter().collect(),
+        }
+    }
+
+    pub fn add_holiday(&mut self, date: MyDateType) -> &mut Self {
+        self.holidays.insert(date);
+        self
+    }
+
+    /// 所有假日(不含周末)
+    pub fn holidays(&self) -> &BTreeSet<MyDateType> {
+        &self.holidays
+    }
+
+    /// 是否节假日, 周末不算
+    pub fn is_holiday(&self, date: &MyDateType) -> bool {
+        self.holidays.contains(date)
+    }
+
+    /// 是否交易日, 即非周末且非节假日
+    pub fn is_trading_day(&self, date: &MyDateType) -> bool {
+        !is_weekend(date) && !self.is_holiday(date)
+    }
+
+    /// date之后(不含date)的第一个交易日
+    pub fn next_trading_day(&self, date: &MyDateType) -> MyDateType {
+        let mut day = tomorrow(date);
+        while !self.is_trading_day(&day) {
+            day = tomorrow(&day);
+        }
+        day
+    }
+
+    /// date之前(不含date)的最后一个交易日
+    pub fn prev_trading_day(&self, date: &MyDateType) -> MyDateType {
+        let mut day = yesterday(date);
+        while !self.is_trading_day(&day) {
+            day = yesterday(&day);
+        }
+        day
+    }
+
+    /// date当天晚上是否有夜盘, 夜盘属于下一个交易日,
+    /// date必须是交易日, 且到下一个交易日之间只隔着周末(周五晚上有夜盘),
+    /// 节假日之前的最后一个交易日晚上没有夜盘
+    pub fn has_night_session(&self, date: &MyDateType) -> bool {
+        if !self.is_trading_day(date) {
+            return false;
+        }
+        let mut day = tomorrow(date);
+        while !self.is_trading_day(&day) {
+            if !is_weekend(&day) {
+                return false;
+            }
+            day = tomorrow(&day);
+        }
+        true
+    }
+}
+
+/// csv文件中的日期, 支持"%Y-%m-%d"及"%Y%m%d"两种格式
+pub(crate) fn parse_csv_date(date_str: &str) -> Result<MyDateType> {
+    let date_str = date_str.trim();
+    parse_date(date_str, "%Y-%m-%d")
+        .or_else(|_| parse_date(date_str, "%Y%m%d"))
+        .map_err(|_| TradeSessionError::TimeFormat(format!("bad date `{}`", date_str)))
+}
+
+/// 如果csv文件只有一列, 则为所有交易所通用的假日
+/// 如果csv文件有两列, 则第一列为交易所名(为空或者"*"表示所有交易所), 第二列为日期
+/// 返回交易所vs日历, 其中ANY_EXCHANGE对应的是通用日历, 每个交易所的日历都包含通用假日
+pub fn load_calendars_from_read<R: Read>(read: R) -> Result<HashMap<String, TradingCalendar>> {
+    let mut common = BTreeSet::new();
+    let mut specific: HashMap<String, BTreeSet<MyDateType>> = HashMap::new();
+    let mut rdr = csv::Reader::from_reader(read);
+
+    for result in rdr.records() {
+        let record = result?;
+
+        let (exchange, date_str) = match record.len() {
+            1 => (ANY_EXCHANGE, &record[0]),
+            2 => (record[0].trim(), &record[1]),
+            _ => {
+                return Err(TradeSessionError::CsvFormat {
+                    line: record.position().map_or(0, |pos| pos.line()),
+                    column: None,
+                    message: format!(
+                        "bad format holiday: expected 1 or 2 fields, got {}, {:#?}",
+                        record.len(),
+                        record
+                    ),
+                });
+            }
+        };
+        let date = parse_csv_date(date_str)?;
+        if exchange.is_empty() || exchange == ANY_EXCHANGE {
+            common.insert(date);
+        } else {
+            specific
+                .entry(exchange.to_string())
+                .or_default()
+                .insert(date);
+        }
+    }
+
+    let mut hash: HashMap<String, TradingCalendar> = specific
+        .into_iter()
+        .map(|(exchange, mut dates)| {
+            dates.extend(common.iter().copied());
+            (exchange, TradingCalendar { holidays: dates })
+        })
+        .collect();
+    hash.insert(
+        ANY_EXCHANGE.to_string(),
+        TradingCalendar { holidays: common },
+    );
+    Ok(hash)
+}
+
+/// 节假日csv文件, 两列, exchange,date, 日期格式为"%Y-%m-%d"或"%Y%m%d"
+/// *,2025-01-01
+/// 如果csv文件只有一列, 则为所有交易所通用的假日
+pub fn load_calendars_from_csv<P: AsRef<Path>>(
+    csv_file_path: P,
+) -> Result<HashMap<String, TradingCalendar>> {
+    let file = open_csv(csv_file_path.as_ref())?;
+    load_calendars_from_read(DecodeReaderBytes::new(file))
+}
+
+/// 打开csv文件, 错误信息包含文件路径
+pub(crate) fn open_csv(path: &Path) -> Result<File> {
+    File::open(path).map_err(|err| {
+        let msg = format!("{} `{}`", err, path.display());
+        TradeSessionError::Io(std::io::Error::new(err.kind(), msg))
+    })
+}
+
+///从csv文件内容加载，参数为csv文件字符串
+pub fn load_calendars_from_csv_content(
+    csv_content: &str,
+) -> Result<HashMap<String, TradingCalendar>> {
+    load_calendars_from_read(csv_content.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn trading_days() -> Result<()> {
+        let csv_str = include_str!("../holidays.csv");
+        let calendars = load_calendars_from_csv_content(csv_str)?;
+        let cal = calendars.get(ANY_EXCHANGE).unwrap();
+
+        // 2025-01-27周一, 春节前最后一个交易日, 没有夜盘
+        assert!(cal.is_trading_day(&make_date(2025, 1, 27)));
+        assert!(!cal.has_night_session(&make_date(2025, 1, 27)));
+        assert!(!cal.is_trading_day(&make_date(2025, 1, 28)));
+        assert_eq!(
+            cal.next_trading_day(&make_date(2025, 1, 27)),
+            make_date(2025, 2, 5)
+        );
+        assert_eq!(
+            cal.prev_trading_day(&make_date(2025, 2, 5)),
+            make_date(2025, 1, 27)
+        );
+
+        // 2025-07-25周五, 有夜盘, 下一个交易日是周一
+        assert!(cal.has_night_session(&make_date(2025, 7, 25)));
+        assert!(!cal.is_trading_day(&make_date(2025, 7, 26)));
+        assert!(!cal.has_night_session(&make_date(2025, 7, 26)));
+        assert_eq!(
+            cal.next_trading_day(&make_date(2025, 7, 25)),
+            make_date(2025, 7, 28)
+        );
+
+        // 2025-04-03周四, 清明节前, 没有夜盘
+        assert!(!cal.has_night_session(&make_date(2025, 4, 3)));
+        Ok(())
+    }
+
+    #[test]
+    fn exchange_holidays() -> Result<()> {
+        let csv_str = "exchange,date\n*,2025-01-01\nSSE,20250102\n";
+        let calendars = load_calendars_from_csv_content(csv_str)?;
+        let sse = calendars.get("SSE").unwrap();
+        assert!(sse.is_holiday(&make_date(2025, 1, 1)));
+        assert!(sse.is_holiday(&make_date(2025, 1, 2)));
+        let any = calendars.get(ANY_EXCHANGE).unwrap();
+        assert!(any.is_holiday(&make_date(2025, 1, 1)));
+        assert!(!any.is_holiday(&make_date(2025, 1, 2)));
+
+        assert!(load_calendars_from_csv_content("date\n2025/01/01\n").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt::Display;
+
+/// 本crate所有可能失败的接口返回的错误
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TradeSessionError {
+    /// 时段不合法, 比如开始时间不早于结束时间
+    InvalidSlice(String),
+    /// 时段重叠, 由TradeSession::try_new_from_slices返回,
+    /// 其他构造方式(post_fix)按交易所的习惯合并重叠或相连的时段, 不返回此错误
+    OverlappingSlice(String),
+    /// 集合竞价时段不合法, 比如撮合开始时间不在时段内, 或跨越anchor
+    InvalidAuction(String),
+    /// 时间或日期格式错误
+    TimeFormat(String),
+    /// 时区名称无效
+    UnknownTimeZone(String),
+    /// 交易时段的json格式错误
+    JsonFormat(String),
+    /// csv文件格式错误, line为行号(从1开始), column为列号(从1开始), 未知时为None
+    CsvFormat {
+        line: u64,
+        column: Option<usize>,
+        message: String,
+    },
+    /// csv文件某一行的错误, line为行号(从1开始), product为该行的品种代码, source为具体的错误
+    CsvRecord {
+        line: u64,
+        product: String,
+        source: Box<TradeSessionError>,
+    },
+    /// 品种不存在, 参数为品种代码或合约代码, 参看SessionManager::try_get_session
+    UnknownProduct(String),
+    /// 文件读写错误, 比如文件不存在
+    Io(std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, TradeSessionError>;
+
+impl Display for TradeSessionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TradeSessionError::InvalidSlice(msg)
+            | TradeSessionError::OverlappingSlice(msg)
+            | TradeSessionError::InvalidAuction(msg)
+            | TradeSessionError::TimeFormat(msg)
+            | TradeSessionError::JsonFormat(msg) => write!(f, "{msg}"),
+            TradeSessionError::UnknownTimeZone(name) => write!(f, "bad time zone `{name}`"),
+            TradeSessionError::CsvFormat {
+                line,
+                column: Some(column),
+                message,
+            } => write!(f, "csv line {line}, column {column}: {message}"),
+            TradeSessionError::CsvFormat {
+                line,
+                column: None,
+                message,
+            } => write!(f, "csv line {line}: {message}"),
+            TradeSessionError::CsvRecord {
+                line,
+                product,
+                source,
+            } => write!(f, "csv line {line}, product `{product}`: {source}"),
+            TradeSessionError::UnknownProduct(product) => {
+                write!(f, "Session for product '{product}' not found")
+            }
+            TradeSessionError::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for TradeSessionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TradeSessionError::Io(err) => Some(err),
+            TradeSessionError::CsvRecord { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for TradeSessionError {
+    fn from(err: std::io::Error) -> Self {
+        TradeSessionError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for TradeSessionError {
+    fn from(err: serde_json::Error) -> Self {
+        TradeSessionError::JsonFormat(err.to_string())
+    }
+}
+
+impl From<csv::Error> for TradeSessionError {
+    fn from(err: csv::Error) -> Self {
+        let line = err.position().map_or(0, |pos| pos.line());
+        let column = match err.kind() {
+            csv::ErrorKind::Utf8 { err, .. } => Some(err.field() + 1),
+            _ => None,
+        };
+        let message = err.to_string();
+        match err.into_kind() {
+            csv::ErrorKind::Io(err) => TradeSessionError::Io(err),
+            _ => TradeSessionError::CsvFormat {
+                line,
+                column,
+                message,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::jcswitch::parse_time_zone;
+    use crate::{SessionManager, SessionSlice, TradeSession, parse_json_slices};
+
+    #[test]
+    fn error_kinds() {
+        assert!(matches!(
+            SessionSlice::new_from_time(10, 0, 9, 0),
+            Err(TradeSessionError::InvalidSlice(_))
+        ));
+        let slices = parse_json_slices(
+            r#"[{"Begin":"09:00","End":"10:15"},{"Begin":"10:00","End":"11:30"}]"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            TradeSession::try_new_from_slices(&slices),
+            Err(TradeSessionError::OverlappingSlice(_))
+        ));
+        assert!(TradeSession::try_new_from_slices(&slices[..1].to_vec()).is_ok());
+        assert!(matches!(
+            parse_json_slices(r#"{"Begin":"09:00"}"#),
+            Err(TradeSessionError::JsonFormat(_))
+        ));
+        assert!(matches!(
+            parse_json_slices(r#"[{"Begin":"9点","End":"10:15"}]"#),
+            Err(TradeSessionError::TimeFormat(_))
+        ));
+        assert!(matches!(
+            parse_time_zone("Asia/Nowhere"),
+            Err(TradeSessionError::UnknownTimeZone(_))
+        ));
+
+        let csv = "product,exchange,session\nag,SHFE,\"[]\"\nCF,CZCE,\"[]\",20250101,x\n";
+        match SessionManager::new_from_csv_content(csv) {
+            Err(TradeSessionError::CsvFormat { line, .. }) => assert_eq!(line, 3),
+            other => panic!("expect CsvFormat, got {:?}", other.map(|_| ())),
+        }
+        let mut s_mgr = SessionManager::new();
+        s_mgr.add_session("ag", TradeSession::new_commodity_session_night());
+        assert!(s_mgr.try_get_session("ag").is_ok());
+        assert!(matches!(
+            s_mgr.try_get_session("XX"),
+            Err(TradeSessionError::UnknownProduct(product)) if product == "XX"
+        ));
+        assert!(matches!(
+            s_mgr.try_get_session_for_instrument("XX2501"),
+            Err(TradeSessionError::UnknownProduct(_))
+        ));
+        match SessionManager::new_from_csv("no/such/file.csv") {
+            Err(err @ TradeSessionError::Io(_)) => {
+                assert!(err.to_string().contains("no/such/file.csv"))
+            }
+            other => panic!("expect Io, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -1,30 +1,36 @@
-use anyhow::Result;
-use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use crate::error::{Result, TradeSessionError};
+use chrono::{
+    DateTime, Datelike, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone,
+    Timelike,
+};
 
 pub type MyDateType = NaiveDate;
 pub type MyDateTimeType = NaiveDateTime;
 pub type MyTimeType = NaiveTime;
+/// IANA时区
+pub type MyTimeZone = chrono_tz::Tz;
+/// 带时区的日期时间, 即一个确定的时间点
+pub type MyZonedType = DateTime<chrono_tz::Tz>;
 
 pub fn make_date(year: i32, month: u32, day: u32) -> MyDateType {
-    return NaiveDate::from_ymd_opt(year, month, day).expect("from_ymd_opt() failed");
+    NaiveDate::from_ymd_opt(year, month, day).expect("from_ymd_opt() failed")
 }
 
 pub fn make_time(hour: u32, min: u32, sec: u32) -> MyTimeType {
-    return NaiveTime::from_hms_opt(hour, min, sec).expect("from_hms_opt() failed");
+    NaiveTime::from_hms_opt(hour, min, sec).expect("from_hms_opt() failed")
 }
 
 pub fn tomorrow(date: &MyDateType) -> MyDateType {
-    return date.succ_opt().expect("succ_opt() failed");
+    date.succ_opt().expect("succ_opt() failed")
 }
 
 pub fn yesterday(date: &MyDateType) -> MyDateType {
-    return date.pred_opt().expect("pred_opt() failed");
+    date.pred_opt().expect("pred_opt() failed")
 }
 
 pub fn date_at_hms(date: &MyDateType, hour: u32, min: u32, sec: u32) -> MyDateTimeType {
-    return date
-        .and_hms_opt(hour, min, sec)
-        .expect("and_hms_opt() failed");
+    date.and_hms_opt(hour, min, sec)
+        .expect("and_hms_opt() failed")
 }
 
 pub fn get_now() -> MyDateTimeType {
@@ -43,7 +49,7 @@ pub fn date_from_days_since_epoch(days_since_epoch: i32) -> MyDateType {
 /// 日期转为从1970-01-01以来的天数
 pub fn date_to_days_since_epoch(date: &MyDateType) -> i32 {
     // 计算自公元1年1月1日以来的天数
-    let days_since_ce = date.num_days_from_ce() as i32;
+    let days_since_ce = date.num_days_from_ce();
     // 计算从Unix Epoch到公元1年1月1日的天数
     let days_from_epoch_to_ce = 719163;
     // 计算从Unix Epoch到指定日期的天数
@@ -80,13 +86,81 @@ pub fn time_to_midnight_nanos(time: &NaiveTime) -> i64 {
 
 /// "%H:%M:%S"
 pub fn parse_time(time_str: &str, fmt: &str) -> Result<MyTimeType> {
-    Ok(NaiveTime::parse_from_str(time_str, fmt)?)
+    NaiveTime::parse_from_str(time_str, fmt)
+        .map_err(|e| TradeSessionError::TimeFormat(format!("bad time `{}`: {}", time_str, e)))
 }
 /// "%Y-%m-%d"
 pub fn parse_date(date_str: &str, fmt: &str) -> Result<MyDateType> {
-    Ok(NaiveDate::parse_from_str(date_str, fmt)?)
+    NaiveDate::parse_from_str(date_str, fmt)
+        .map_err(|e| TradeSessionError::TimeFormat(format!("bad date `{}`: {}", date_str, e)))
 }
 /// "%Y-%m-%d %H:%M:%S"
 pub fn parse_datetime(datetime_str: &str, fmt: &str) -> Result<MyDateTimeType> {
-    Ok(NaiveDateTime::parse_from_str(datetime_str, fmt)?)
+    NaiveDateTime::parse_from_str(datetime_str, fmt).map_err(|e| {
+        TradeSessionError::TimeFormat(format!("bad datetime `{}`: {}", datetime_str, e))
+    })
+}
+
+/// 由日期和时间组合成日期时间
+pub fn make_datetime(date: &MyDateType, time: &MyTimeType) -> MyDateTimeType {
+    NaiveDateTime::new(*date, *time)
+}
+
+/// 是否周六或周日
+pub fn is_weekend(date: &MyDateType) -> bool {
+    date.weekday().number_from_monday() >= 6
+}
+
+/// 日期时间加上纳秒数, 可以为负数
+pub fn datetime_add_nanos(datetime: &MyDateTimeType, nanos: i64) -> MyDateTimeType {
+    *datetime + chrono::TimeDelta::nanoseconds(nanos)
+}
+
+/// IANA时区名, 比如"Asia/Shanghai", "America/Chicago", "UTC"
+pub fn parse_time_zone(name: &str) -> Result<MyTimeZone> {
+    name.parse::<chrono_tz::Tz>()
+        .map_err(|_| TradeSessionError::UnknownTimeZone(name.to_string()))
+}
+
+/// IANA时区名
+pub fn time_zone_name(tz: &MyTimeZone) -> String {
+    tz.name().to_string()
+}
+
+/// 时间点所带的时区
+pub fn zoned_time_zone(zoned: &MyZonedType) -> MyTimeZone {
+    zoned.timezone()
+}
+
+/// 时间点在时区tz的当地日期时间
+pub fn zoned_to_local(zoned: &MyZonedType, tz: &MyTimeZone) -> MyDateTimeType {
+    zoned.with_timezone(tz).naive_local()
+}
+
+/// 时区tz的当地日期时间转为时间点, 夏令时开始时跳过的时间按切换前的偏移量计算(即顺延),
+/// 夏令时结束时重复的时间取较早的
+pub fn local_to_zoned(datetime: &MyDateTimeType, tz: &MyTimeZone) -> MyZonedType {
+    match tz.from_local_datetime(datetime) {
+        LocalResult::Single(zoned) => zoned,
+        LocalResult::Ambiguous(earliest, _) => earliest,
+        LocalResult::None => {
+            let before = tz
+                .offset_from_utc_datetime(&(*datetime - chrono::TimeDelta::days(1)))
+                .fix()
+                .local_minus_utc();
+            tz.from_utc_datetime(&(*datetime - chrono::TimeDelta::seconds(before as i64)))
+        }
+    }
+}
+
+/// 从1970-01-01 00:00:00 UTC以来的纳秒总数构造时区tz的时间点
+pub fn zoned_from_timestamp_nanos(nanos: i64, tz: &MyTimeZone) -> MyZonedType {
+    DateTime::from_timestamp_nanos(nanos).with_timezone(tz)
+}
+
+/// 从1970-01-01 00:00:00 UTC以来的纳秒总数
+pub fn zoned_to_timestamp_nanos(zoned: &MyZonedType) -> i64 {
+    zoned
+        .timestamp_nanos_opt()
+        .expect("timestamp_nanos_opt() failed")
 }
@@ -1,5 +1,7 @@
-use anyhow::Result;
+use crate::error::{Result, TradeSessionError};
 use jiff::civil::{Date, DateTime, Time};
+use jiff::tz::TimeZone;
+use jiff::{Timestamp, Zoned};
 
 pub type MyDateType = Date;
 
@@ -7,26 +9,32 @@ pub type MyDateTimeType = DateTime;
 
 pub type MyTimeType = Time;
 
+/// IANA时区
+pub type MyTimeZone = TimeZone;
+
+/// 带时区的日期时间, 即一个确定的时间点
+pub type MyZonedType = Zoned;
+
 //////////////////////////////////////////////////////////////////////////////////////////////////
 
 pub fn make_date(year: i32, month: u32, day: u32) -> MyDateType {
-    return Date::constant(year as i16, month as i8, day as i8);
+    Date::constant(year as i16, month as i8, day as i8)
 }
 
 pub fn make_time(hour: u32, minute: u32, second: u32) -> MyTimeType {
-    return Time::constant(hour as i8, minute as i8, second as i8, 0);
+    Time::constant(hour as i8, minute as i8, second as i8, 0)
 }
 
 pub fn tomorrow(date: &MyDateType) -> MyDateType {
-    return date.tomorrow().expect("tomorrow() failed");
+    date.tomorrow().expect("tomorrow() failed")
 }
 
 pub fn yesterday(date: &MyDateType) -> MyDateType {
-    return date.yesterday().expect("yesterday() failed");
+    date.yesterday().expect("yesterday() failed")
 }
 
 pub fn date_at_hms(date: &MyDateType, hour: u32, minute: u32, second: u32) -> MyDateTimeType {
-    return date.at(hour as i8, minute as i8, second as i8, 0);
+    date.at(hour as i8, minute as i8, second as i8, 0)
 }
 
 pub fn get_now() -> MyDateTimeType {
@@ -82,8 +90,7 @@ pub fn date_to_days_since_epoch(date: &MyDateType) -> i32 {
     let year_of_era = year % (400);
     let day_of_year = (153 * month + 2) / (5) + day - (1);
     let day_of_era = year_of_era * (365) + year_of_era / (4) - year_of_era / (100) + day_of_year;
-    let epoch_days = era * DAYS_IN_ERA + day_of_era - DAYS_FROM_0000_01_01_TO_1970_01_01;
-    epoch_days
+    era * DAYS_IN_ERA + day_of_era - DAYS_FROM_0000_01_01_TO_1970_01_01
 }
 
 /// 从1970-01-01 00:00:00以来的纳秒总数构造日期时间
@@ -163,13 +170,72 @@ pub fn time_to_midnight_nanos(time: &MyTimeType) -> i64 {
 
 /// "%H:%M:%S"
 pub fn parse_time(time_str: &str, fmt: &str) -> Result<MyTimeType> {
-    Ok(Time::strptime(fmt, time_str)?)
+    Time::strptime(fmt, time_str)
+        .map_err(|e| TradeSessionError::TimeFormat(format!("bad time `{}`: {}", time_str, e)))
 }
 /// "%Y-%m-%d"
 pub fn parse_date(date_str: &str, fmt: &str) -> Result<MyDateType> {
-    Ok(Date::strptime(fmt, date_str)?)
+    Date::strptime(fmt, date_str)
+        .map_err(|e| TradeSessionError::TimeFormat(format!("bad date `{}`: {}", date_str, e)))
 }
 /// "%Y-%m-%d %H:%M:%S"
 pub fn parse_datetime(datetime_str: &str, fmt: &str) -> Result<MyDateTimeType> {
-    Ok(DateTime::strptime(fmt, datetime_str)?)
+    DateTime::strptime(fmt, datetime_str).map_err(|e| {
+        TradeSessionError::TimeFormat(format!("bad datetime `{}`: {}", datetime_str, e))
+    })
+}
+
+/// 由日期和时间组合成日期时间
+pub fn make_datetime(date: &MyDateType, time: &MyTimeType) -> MyDateTimeType {
+    DateTime::from_parts(*date, *time)
+}
+
+/// 是否周六或周日
+pub fn is_weekend(date: &MyDateType) -> bool {
+    date.weekday().to_monday_one_offset() >= 6
+}
+
+/// 日期时间加上纳秒数, 可以为负数
+pub fn datetime_add_nanos(datetime: &MyDateTimeType, nanos: i64) -> MyDateTimeType {
+    datetime
+        .checked_add(jiff::SignedDuration::from_nanos(nanos))
+        .expect("checked_add() failed")
+}
+
+/// IANA时区名, 比如"Asia/Shanghai", "America/Chicago", "UTC", 使用系统的时区数据库
+pub fn parse_time_zone(name: &str) -> Result<MyTimeZone> {
+    TimeZone::get(name).map_err(|_| TradeSessionError::UnknownTimeZone(name.to_string()))
+}
+
+/// IANA时区名, 不是IANA时区时为空字符串
+pub fn time_zone_name(tz: &MyTimeZone) -> String {
+    tz.iana_name().unwrap_or_default().to_string()
+}
+
+/// 时间点所带的时区
+pub fn zoned_time_zone(zoned: &MyZonedType) -> MyTimeZone {
+    zoned.time_zone().clone()
+}
+
+/// 时间点在时区tz的当地日期时间
+pub fn zoned_to_local(zoned: &MyZonedType, tz: &MyTimeZone) -> MyDateTimeType {
+    tz.to_datetime(zoned.timestamp())
+}
+
+/// 时区tz的当地日期时间转为时间点, 夏令时开始时跳过的时间按切换前的偏移量计算(即顺延),
+/// 夏令时结束时重复的时间取较早的
+pub fn local_to_zoned(datetime: &MyDateTimeType, tz: &MyTimeZone) -> MyZonedType {
+    datetime.to_zoned(tz.clone()).expect("to_zoned() failed")
+}
+
+/// 从1970-01-01 00:00:00 UTC以来的纳秒总数构造时区tz的时间点
+pub fn zoned_from_timestamp_nanos(nanos: i64, tz: &MyTimeZone) -> MyZonedType {
+    Timestamp::from_nanosecond(nanos as i128)
+        .expect("from_nanosecond() failed")
+        .to_zoned(tz.clone())
+}
+
+/// 从1970-01-01 00:00:00 UTC以来的纳秒总数
+pub fn zoned_to_timestamp_nanos(zoned: &MyZonedType) -> i64 {
+    zoned.timestamp().as_nanosecond() as i64
 }
@@ -1,6 +1,22 @@
+mod bar;
+mod bitmap;
+mod calendar;
+mod error;
 pub mod jcswitch;
+mod phase;
+mod serialize;
 mod session_mgr;
+mod set_ops;
+mod spread;
 mod tradesession;
+mod validate;
+mod zoned;
 
+pub use bar::*;
+pub use calendar::*;
+pub use error::*;
+pub use phase::*;
 pub use session_mgr::*;
+pub use spread::*;
 pub use tradesession::*;
+pub use validate::*;
@@ -0,0 +1,268 @@
+use crate::error::{Result, TradeSessionError};
+use crate::jcswitch::*;
+use crate::tradesession::{DEFAULT_ANCHOR, SECS_IN_ONE_DAY, ShiftedTime};
+
+/// 交易阶段
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TradingPhase {
+    /// 开盘集合竞价报单阶段, 比如期货08:55~08:59, 股票09:15~09:25
+    PreOpenAuction,
+    /// 开盘集合竞价撮合阶段, 比如期货08:59~09:00
+    AuctionMatch,
+    /// 开盘集合竞价撮合之后、连续交易之前, 接受报单但暂不处理, 连续交易开始时撮合, 比如股票09:25~09:30
+    PreOpenQueue,
+    /// 连续交易
+    Continuous,
+    /// 盘中休息, 比如10:15~10:30, 11:30~13:00
+    Break,
+    /// 收盘集合竞价, 比如股票14:57~15:00
+    ClosingAuction,
+    /// 休市
+    Closed,
+}
+
+/// 报单在某个交易阶段的处理方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OrderHandling {
+    /// 接受并立即参与撮合
+    Accepted,
+    /// 接受, 排队等待集合竞价撮合
+    Queued,
+    /// 拒绝
+    Rejected,
+}
+
+impl TradingPhase {
+    /// 报单的处理方式, 盘中休息时各交易所规则不一, 保守起见视为拒绝
+    pub fn order_handling(&self) -> OrderHandling {
+        match self {
+            TradingPhase::Continuous => OrderHandling::Accepted,
+            TradingPhase::PreOpenAuction
+            | TradingPhase::PreOpenQueue
+            | TradingPhase::ClosingAuction => OrderHandling::Queued,
+            TradingPhase::AuctionMatch | TradingPhase::Break | TradingPhase::Closed => {
+                OrderHandling::Rejected
+            }
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TradingPhase::PreOpenAuction => "PreOpenAuction",
+            TradingPhase::AuctionMatch => "AuctionMatch",
+            TradingPhase::PreOpenQueue => "PreOpenQueue",
+            TradingPhase::Continuous => "Continuous",
+            TradingPhase::Break => "Break",
+            TradingPhase::ClosingAuction => "ClosingAuction",
+            TradingPhase::Closed => "Closed",
+        }
+    }
+}
+
+/// 集合竞价时段, 含开始，不含结束, 所有时间平移到以anchor为0点, 默认增加4小时, 参看ShiftedTime
+/// 开盘集合竞价: [begin, match_begin)报单, [match_begin, end)撮合, end一般是连续交易的开始
+/// 收盘集合竞价: [begin, end)报单, end时撮合, match_begin等于end
+/// 排队的开盘集合竞价: [begin, match_begin)报单, match_begin时撮合, [match_begin, end)接受报单并排队
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AuctionWindow {
+    closing: bool,
+    /// 撮合之后到连续交易之前接受报单并排队, 比如股票09:25~09:30
+    queued: bool,
+    begin: ShiftedTime,
+    match_begin: ShiftedTime,
+    end: ShiftedTime,
+    /// 交易日开始时间, 自零点起的秒数
+    anchor: u32,
+}
+
+impl AuctionWindow {
+    /// 开盘集合竞价, 原始时间, 比如期货(08:55, 08:59, 09:00), 股票(09:15, 09:25, 09:30)
+    pub fn new_open(
+        begin: &MyTimeType,
+        match_begin: &MyTimeType,
+        end: &MyTimeType,
+    ) -> Result<Self> {
+        Self::new_from_shifted(
+            false,
+            ShiftedTime::from(begin),
+            ShiftedTime::from(match_begin),
+            ShiftedTime::from(end),
+        )
+    }
+
+    /// 撮合之后排队的开盘集合竞价, 原始时间, 比如股票(09:15, 09:25, 09:30),
+    /// 09:25撮合, 09:25~09:30接受报单但暂不处理, 参看TradingPhase::PreOpenQueue
+    pub fn new_open_queued(
+        begin: &MyTimeType,
+        match_begin: &MyTimeType,
+        end: &MyTimeType,
+    ) -> Result<Self> {
+        Ok(Self::new_open(begin, match_begin, end)?.with_queued(true))
+    }
+
+    /// 收盘集合竞价, 原始时间, 比如股票(14:57, 15:00)
+    pub fn new_close(begin: &MyTimeType, end: &MyTimeType) -> Result<Self> {
+        let end = ShiftedTime::from(end);
+        Self::new_from_shifted(true, ShiftedTime::from(begin), end, end)
+    }
+
+    /// 原始时间, anchor为交易日开始时间(自零点起的秒数), 结束(或撮合)时间等于anchor时视为交易日结束
+    pub fn new_anchored(
+        closing: bool,
+        begin: &MyTimeType,
+        match_begin: &MyTimeType,
+        end: &MyTimeType,
+        anchor: u32,
+    ) -> Result<Self> {
+        let begin = ShiftedTime::from_time_anchored(begin, anchor);
+        let shift = |t: &MyTimeType| match ShiftedTime::from_time_anchored(t, anchor) {
+            ShiftedTime(0) if begin.seconds() > 0 => ShiftedTime(SECS_IN_ONE_DAY),
+            sec => sec,
+        };
+        Self::new_from_shifted_anchored(closing, begin, shift(match_begin), shift(end), anchor)
+    }
+
+    /// 注意： 输入数据必须已经加过4小时了, 须begin <= match_begin <= end且begin < end
+    pub fn new_from_shifted(
+        closing: bool,
+        begin: ShiftedTime,
+        match_begin: ShiftedTime,
+        end: ShiftedTime,
+    ) -> Result<Self> {
+        Self::new_from_shifted_anchored(closing, begin, match_begin, end, DEFAULT_ANCHOR)
+    }
+
+    /// 注意： 输入数据必须已经平移到以anchor为0点, 须begin <= match_begin <= end且begin < end
+    pub fn new_from_shifted_anchored(
+        closing: bool,
+        begin: ShiftedTime,
+        match_begin: ShiftedTime,
+        end: ShiftedTime,
+        anchor: u32,
+    ) -> Result<Self> {
+        if begin >= end || match_begin < begin || match_begin > end {
+            return Err(TradeSessionError::InvalidAuction(format!(
+                "AuctionWindow: expect begin <= match_begin <= end, but got begin: {}, match_begin: {}, end: {}",
+                begin, match_begin, end
+            )));
+        }
+        Ok(Self {
+            closing,
+            queued: false,
+            begin,
+            match_begin,
+            end,
+            anchor: anchor % SECS_IN_ONE_DAY,
+        })
+    }
+
+    /// 平移到以另一个anchor为0点, 跨越新anchor时返回错误
+    pub fn rebase(&self, anchor: u32) -> Result<Self> {
+        let anchor = anchor % SECS_IN_ONE_DAY;
+        if anchor == self.anchor {
+            return Ok(*self);
+        }
+        let shift = |t: ShiftedTime| {
+            let nominal = t.nominal_seconds_anchored(self.anchor);
+            ShiftedTime::new_from_midnight_seconds_anchored(nominal, anchor).seconds()
+        };
+        let begin = shift(self.begin);
+        let duration = self.end.seconds() - self.begin.seconds();
+        let match_offset = self.match_begin.seconds() - self.begin.seconds();
+        if begin + duration > SECS_IN_ONE_DAY {
+            return Err(TradeSessionError::InvalidAuction(format!(
+                "AuctionWindow: {} ~ {} crosses the anchor",
+                self.begin, self.end
+            )));
+        }
+        let auction = Self::new_from_shifted_anchored(
+            self.closing,
+            ShiftedTime(begin),
+            ShiftedTime(begin + match_offset),
+            ShiftedTime(begin + duration),
+            anchor,
+        )?;
+        Ok(auction.with_queued(self.queued))
+    }
+
+    /// 设置撮合之后是否排队, 参看new_open_queued
+    pub(crate) fn with_queued(self, queued: bool) -> Self {
+        Self { queued, ..self }
+    }
+
+    /// 是否收盘集合竞价
+    pub fn is_closing(&self) -> bool {
+        self.closing
+    }
+    /// 是否撮合之后排队的开盘集合竞价, 参看new_open_queued
+    pub fn is_queued(&self) -> bool {
+        self.queued
+    }
+    /// 注意：超前4小时
+    pub fn begin(&self) -> ShiftedTime {
+        self.begin
+    }
+    /// 注意：超前4小时
+    pub fn match_begin(&self) -> ShiftedTime {
+        self.match_begin
+    }
+    /// 注意：超前4小时
+    pub fn end(&self) -> ShiftedTime {
+        self.end
+    }
+    /// 交易日开始时间, 自零点起的秒数
+    pub fn anchor(&self) -> u32 {
+        self.anchor
+    }
+
+    /// sec所处的集合竞价阶段, 不在此时段内时返回None
+    pub fn phase_at(&self, sec: ShiftedTime) -> Option<TradingPhase> {
+        if sec < self.begin || sec >= self.end {
+            None
+        } else if self.closing {
+            Some(TradingPhase::ClosingAuction)
+        } else if sec < self.match_begin {
+            Some(TradingPhase::PreOpenAuction)
+        } else if self.queued {
+            Some(TradingPhase::PreOpenQueue)
+        } else {
+            Some(TradingPhase::AuctionMatch)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn auction_window() -> Result<()> {
+        let open = AuctionWindow::new_open(
+            &make_time(20, 55, 0),
+            &make_time(20, 59, 0),
+            &make_time(21, 0, 0),
+        )?;
+        let at = |h, m| ShiftedTime::from(&make_time(h, m, 0));
+        assert_eq!(open.phase_at(at(20, 54)), None);
+        assert_eq!(
+            open.phase_at(at(20, 55)),
+            Some(TradingPhase::PreOpenAuction)
+        );
+        assert_eq!(open.phase_at(at(20, 59)), Some(TradingPhase::AuctionMatch));
+        assert_eq!(open.phase_at(at(21, 0)), None);
+
+        let close = AuctionWindow::new_close(&make_time(14, 57, 0), &make_time(15, 0, 0))?;
+        assert_eq!(
+            close.phase_at(at(14, 59)),
+            Some(TradingPhase::ClosingAuction)
+        );
+        assert_eq!(
+            TradingPhase::ClosingAuction.order_handling(),
+            OrderHandling::Queued
+        );
+
+        assert!(AuctionWindow::new_close(&make_time(15, 0, 0), &make_time(14, 57, 0)).is_err());
+        Ok(())
+    }
+}
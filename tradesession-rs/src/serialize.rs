@@ -0,0 +1,395 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::de::Error as _;
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::jcswitch::*;
+use crate::phase::AuctionWindow;
+use crate::session_mgr::{SessionManager, futures_session};
+use crate::tradesession::{
+    DEFAULT_ANCHOR, NightRule, SessionSlice, ShiftedTime, TradeSession, parse_slice_time,
+};
+
+/// 同parse_json_slices读取的格式, {"Begin":"09:00:00","End":"10:15:00"}, 时间为实际时间,
+/// anchor不是DEFAULT_ANCHOR时增加"Anchor":"17:00:00", 读取时也接受小写的键及"%H:%M"格式的时间
+#[derive(Serialize, Deserialize)]
+struct JsonSlice {
+    #[serde(rename = "Begin", alias = "begin")]
+    begin: String,
+    #[serde(rename = "End", alias = "end")]
+    end: String,
+    #[serde(
+        rename = "Anchor",
+        alias = "anchor",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    anchor: Option<String>,
+}
+
+/// 集合竞价时段, 时间为实际时间, 按所在session的anchor平移
+#[derive(Serialize, Deserialize)]
+struct JsonAuction {
+    #[serde(rename = "Begin", alias = "begin")]
+    begin: String,
+    #[serde(rename = "MatchBegin", alias = "match_begin")]
+    match_begin: String,
+    #[serde(rename = "End", alias = "end")]
+    end: String,
+    #[serde(
+        rename = "Closing",
+        alias = "closing",
+        default,
+        skip_serializing_if = "is_false"
+    )]
+    closing: bool,
+    #[serde(
+        rename = "Queued",
+        alias = "queued",
+        default,
+        skip_serializing_if = "is_false"
+    )]
+    queued: bool,
+}
+
+/// 带anchor、时区、夜盘规则或非期货规则集合竞价时段的session, 都是缺省值的字段不写入,
+/// 没有Auctions时按期货规则添加集合竞价(同csv加载), "Auctions":[]表示没有集合竞价
+#[derive(Serialize, Deserialize)]
+struct JsonSession {
+    #[serde(rename = "Slices", alias = "slices")]
+    slices: Vec<SessionSlice>,
+    #[serde(
+        rename = "Anchor",
+        alias = "anchor",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    anchor: Option<String>,
+    #[serde(
+        rename = "Auctions",
+        alias = "auctions",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    auctions: Option<Vec<JsonAuction>>,
+    #[serde(
+        rename = "TimeZone",
+        alias = "time_zone",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    time_zone: Option<String>,
+    #[serde(
+        rename = "NightRule",
+        alias = "night_rule",
+        default,
+        skip_serializing_if = "is_default_night_rule"
+    )]
+    night_rule: NightRule,
+}
+
+/// 读取时接受只有各时段的数组, 或者JsonSession
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonSessionForm {
+    Slices(Vec<SessionSlice>),
+    Full(JsonSession),
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_default_night_rule(rule: &NightRule) -> bool {
+    *rule == NightRule::default()
+}
+
+/// anchor(自零点起的秒数)的实际时间, 等于DEFAULT_ANCHOR时为None
+fn anchor_text(anchor: u32) -> Option<String> {
+    (anchor != DEFAULT_ANCHOR).then(|| ShiftedTime(0).nominal_time_anchored(anchor).to_string())
+}
+
+/// 读取anchor, 没有时为DEFAULT_ANCHOR
+fn parse_anchor(text: Option<&str>) -> crate::error::Result<u32> {
+    match text {
+        Some(text) => Ok(ShiftedTime::from_time_anchored(&parse_slice_time(text)?, 0).seconds()),
+        None => Ok(DEFAULT_ANCHOR),
+    }
+}
+
+impl Serialize for SessionSlice {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let anchor = self.anchor();
+        JsonSlice {
+            begin: self.begin().nominal_time_anchored(anchor).to_string(),
+            end: self.end().nominal_time_anchored(anchor).to_string(),
+            anchor: anchor_text(anchor),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SessionSlice {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = JsonSlice::deserialize(deserializer)?;
+        let begin = parse_slice_time(&json.begin).map_err(D::Error::custom)?;
+        let end = parse_slice_time(&json.end).map_err(D::Error::custom)?;
+        let anchor = parse_anchor(json.anchor.as_deref()).map_err(D::Error::custom)?;
+        SessionSlice::new_anchored(&begin, &end, anchor).map_err(D::Error::custom)
+    }
+}
+
+/// anchor为DEFAULT_ANCHOR, 没有时区, 夜盘规则为缺省值, 且集合竞价时段同add_futures_auctions时,
+/// 只写入各时段, 格式同parse_json_slices, 否则写入JsonSession, 时区不是IANA时区时返回错误
+impl Serialize for TradeSession {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let anchor = self.anchor_seconds();
+        let time_zone = match self.time_zone() {
+            Some(tz) => match time_zone_name(tz) {
+                name if name.is_empty() => {
+                    return Err(S::Error::custom("time zone without an IANA name"));
+                }
+                name => Some(name),
+            },
+            None => None,
+        };
+        let futures_auctions = self.auctions() == futures_session(self.get_slices()).auctions();
+        if anchor == DEFAULT_ANCHOR
+            && futures_auctions
+            && time_zone.is_none()
+            && is_default_night_rule(&self.night_rule())
+        {
+            return serializer.collect_seq(self.get_slices());
+        }
+        let nominal = |sec: ShiftedTime| sec.nominal_time_anchored(anchor).to_string();
+        JsonSession {
+            slices: self.get_slices().clone(),
+            anchor: anchor_text(anchor),
+            auctions: (!futures_auctions).then(|| {
+                self.auctions()
+                    .iter()
+                    .map(|auction| JsonAuction {
+                        begin: nominal(auction.begin()),
+                        match_begin: nominal(auction.match_begin()),
+                        end: nominal(auction.end()),
+                        closing: auction.is_closing(),
+                        queued: auction.is_queued(),
+                    })
+                    .collect()
+            }),
+            time_zone,
+            night_rule: self.night_rule(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// 只有各时段时同csv及json加载, 按期货规则添加集合竞价时段
+impl<'de> Deserialize<'de> for TradeSession {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = match JsonSessionForm::deserialize(deserializer)? {
+            JsonSessionForm::Slices(slices) => return Ok(futures_session(&slices)),
+            JsonSessionForm::Full(json) => json,
+        };
+        let anchor = parse_anchor(json.anchor.as_deref()).map_err(D::Error::custom)?;
+        let mut session =
+            TradeSession::new_with_anchor(&ShiftedTime(0).nominal_time_anchored(anchor));
+        for slice in json.slices {
+            session.add_slice_directly(slice);
+        }
+        session.post_fix();
+        match &json.auctions {
+            None => {
+                session.add_futures_auctions();
+            }
+            Some(auctions) => {
+                for auction in auctions {
+                    let parse = |text: &str| parse_slice_time(text).map_err(D::Error::custom);
+                    let window = AuctionWindow::new_anchored(
+                        auction.closing,
+                        &parse(&auction.begin)?,
+                        &parse(&auction.match_begin)?,
+                        &parse(&auction.end)?,
+                        anchor,
+                    )
+                    .map_err(D::Error::custom)?;
+                    session
+                        .add_auction(window.with_queued(auction.queued))
+                        .map_err(D::Error::custom)?;
+                }
+            }
+        }
+        if let Some(name) = &json.time_zone {
+            session.set_time_zone(&parse_time_zone(name).map_err(D::Error::custom)?);
+        }
+        session.set_night_rule(json.night_rule);
+        Ok(session)
+    }
+}
+
+/// 品种 vs 最新版本的时段(含各自的时区), 按品种代码排序, 历史版本、交易所、日历及交易所的时区设置都不写入,
+/// 读取时同SessionManager::new_from_map
+impl Serialize for SessionManager {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let sessions: BTreeMap<&String, &TradeSession> = self.session_map().iter().collect();
+        sessions.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SessionManager {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let sessions = HashMap::<String, TradeSession>::deserialize(deserializer)?;
+        Ok(SessionManager::new_from_map(sessions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::tradesession::parse_json_slices;
+
+    /// 每30秒比较一次phase_at, 以及anchor、时区、夜盘规则和集合竞价时段
+    fn assert_same(loaded: &TradeSession, session: &TradeSession) {
+        let spans = |session: &TradeSession| {
+            let slices = session.get_slices().iter();
+            slices
+                .map(|s| (s.begin(), s.end(), s.anchor()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(spans(loaded), spans(session));
+        assert_eq!(loaded.anchor(), session.anchor());
+        assert_eq!(loaded.auctions(), session.auctions());
+        assert_eq!(
+            loaded.time_zone().map(time_zone_name),
+            session.time_zone().map(time_zone_name)
+        );
+        assert_eq!(loaded.night_rule(), session.night_rule());
+        assert_eq!(loaded.minute_bars(true), session.minute_bars(true));
+        for secs in (0..86400).step_by(30) {
+            let ts = time_from_midnight_nanos(secs * 1_000_000_000);
+            assert_eq!(loaded.phase_at(&ts), session.phase_at(&ts), "{ts}");
+        }
+    }
+
+    #[test]
+    fn serde_round_trip() -> anyhow::Result<()> {
+        let json = r#"[{"Begin":"21:00:00","End":"02:30:00"},{"Begin":"09:00:00","End":"10:15:00"},{"Begin":"10:30:00","End":"11:30:00"},{"Begin":"13:30:00","End":"15:00:00"}]"#;
+        // 按期货规则添加的集合竞价时段不写入, 读取时同csv加载, 添加期货集合竞价时段
+        let session = TradeSession::new_commodity_session_night();
+        assert_eq!(serde_json::to_string(&session)?, json);
+        let loaded: TradeSession = serde_json::from_str(json)?;
+        assert_same(&loaded, &session);
+        assert_eq!(loaded.minutes_list(), session.minutes_list());
+        assert_eq!(loaded.day_begin(), session.day_begin());
+        assert_eq!(loaded.morning_begin(), session.morning_begin());
+
+        // 其他集合竞价时段写入JsonSession, 没有集合竞价时为"Auctions":[]
+        let stock = TradeSession::new_stock_session();
+        let text = serde_json::to_string(&stock)?;
+        assert!(text.contains(r#""Auctions":[{"Begin":"09:15:00","MatchBegin":"09:25:00","End":"09:30:00","Queued":true}"#));
+        assert_same(&serde_json::from_str(&text)?, &stock);
+        let mut quiet = TradeSession::new();
+        quiet.add_slice(9, 0, 10, 15)?;
+        quiet.post_fix();
+        let text = serde_json::to_string(&quiet)?;
+        assert_eq!(
+            text,
+            r#"{"Slices":[{"Begin":"09:00:00","End":"10:15:00"}],"Auctions":[]}"#
+        );
+        assert_same(&serde_json::from_str(&text)?, &quiet);
+
+        // 与parse_json_slices的格式兼容
+        let lower = r#"[{"begin":"09:00","end":"10:15"}]"#;
+        let loaded: TradeSession = serde_json::from_str(lower)?;
+        let slices = parse_json_slices(lower)?;
+        assert_eq!(
+            serde_json::to_string(&loaded)?,
+            serde_json::to_string(&slices)?
+        );
+        assert_eq!(loaded.auctions().len(), 1);
+        assert!(
+            serde_json::from_str::<TradeSession>(r#"[{"Begin":"10:00","End":"09:00"}]"#).is_err()
+        );
+        assert!(serde_json::from_str::<SessionSlice>(r#"{"Begin":"9点","End":"10:00"}"#).is_err());
+
+        // 从csv加载的session写入后, 仍可以用parse_json_slices读取
+        let s_mgr = SessionManager::new_from_csv_content(include_str!("../tradesession.csv"))?;
+        let text = serde_json::to_string(&s_mgr)?;
+        let values: BTreeMap<String, serde_json::Value> = serde_json::from_str(&text)?;
+        for (product, value) in &values {
+            let slices = parse_json_slices(&value.to_string())?;
+            assert_eq!(
+                slices.len(),
+                s_mgr.get_session(product).unwrap().get_slices().len()
+            );
+        }
+        let loaded: SessionManager = serde_json::from_str(&text)?;
+        assert_eq!(serde_json::to_string(&loaded)?, text);
+        for (product, session) in s_mgr.session_map() {
+            assert_same(loaded.get_session(product).unwrap(), session);
+        }
+        assert!(
+            loaded
+                .get_session("ag")
+                .unwrap()
+                .in_session(&make_time(1, 0, 0), true, false)
+        );
+
+        // 嵌入在其他配置结构中
+        #[derive(Serialize, Deserialize)]
+        struct Config {
+            name: String,
+            session: TradeSession,
+        }
+        let config: Config = serde_json::from_str(&format!(r#"{{"name":"ag","session":{json}}}"#))?;
+        assert_eq!(config.name, "ag");
+        assert!(config.session.has_night());
+        Ok(())
+    }
+
+    #[test]
+    fn serde_anchor() -> anyhow::Result<()> {
+        // CME: 17:00开始交易日, 芝加哥时间, 周日晚上开市, 收盘集合竞价15:55~16:00
+        let mut cme = TradeSession::new_with_anchor(&make_time(17, 0, 0));
+        cme.add_slice(17, 0, 16, 0)?;
+        cme.post_fix();
+        cme.set_time_zone(&parse_time_zone("America/Chicago")?)
+            .set_night_rule(NightRule::BeforeTradingDay)
+            .add_auction(AuctionWindow::new_close(
+                &make_time(15, 55, 0),
+                &make_time(16, 0, 0),
+            )?)?;
+        let text = serde_json::to_string(&cme)?;
+        assert_eq!(
+            text,
+            r#"{"Slices":[{"Begin":"17:00:00","End":"16:00:00","Anchor":"17:00:00"}],"Anchor":"17:00:00","Auctions":[{"Begin":"15:55:00","MatchBegin":"16:00:00","End":"16:00:00","Closing":true}],"TimeZone":"America/Chicago","NightRule":"BeforeTradingDay"}"#
+        );
+        assert_same(&serde_json::from_str(&text)?, &cme);
+        let slice = cme.get_slices()[0];
+        let loaded: SessionSlice = serde_json::from_str(&serde_json::to_string(&slice)?)?;
+        assert_eq!(
+            (loaded.begin(), loaded.end(), loaded.anchor()),
+            (slice.begin(), slice.end(), slice.anchor())
+        );
+
+        // 结束于anchor的时段
+        let mut evening = TradeSession::new();
+        evening.add_slice(19, 0, 20, 0)?;
+        evening.post_fix();
+        evening.add_futures_auctions();
+        let text = serde_json::to_string(&evening)?;
+        assert_eq!(text, r#"[{"Begin":"19:00:00","End":"20:00:00"}]"#);
+        assert_same(&serde_json::from_str(&text)?, &evening);
+
+        // anchor为0:00的全天交易
+        let mut crypto = TradeSession::new_with_anchor(&make_time(0, 0, 0));
+        crypto.add_slice(0, 0, 0, 0)?;
+        crypto.post_fix();
+        let loaded: TradeSession = serde_json::from_str(&serde_json::to_string(&crypto)?)?;
+        assert_same(&loaded, &crypto);
+        assert!(loaded.in_session(&make_time(23, 59, 59), true, false));
+        Ok(())
+    }
+}
@@ -1,42 +1,186 @@
-use anyhow::{Context, Result, anyhow};
 use encoding_rs_io::DecodeReaderBytes;
-use std::fs::File;
 use std::path::Path;
-use std::{collections::HashMap, io::Read};
+use std::time::Duration;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Read,
+};
 
-use crate::jcswitch::MyTimeType;
+use crate::calendar::*;
+use crate::error::{Result, TradeSessionError};
+use crate::jcswitch::{
+    MyDateTimeType, MyDateType, MyTimeType, MyTimeZone, MyZonedType, make_datetime, tomorrow,
+};
+use crate::phase::TradingPhase;
 
 use super::tradesession::*;
 
+/// 同一品种的各个版本, key为生效日期(含), None表示最早的版本(没有生效日期)
+pub type SessionHistory = BTreeMap<Option<MyDateType>, TradeSession>;
+
+/// 品种信息, 对应csv文件中同一交易所同一品种的所有行
+#[derive(Clone, Debug)]
+pub struct ProductRecord {
+    product: String,
+    /// 交易所未知时(比如两列的csv文件)为空字符串
+    exchange: String,
+    history: SessionHistory,
+}
+
+impl ProductRecord {
+    pub fn new(product: &str, exchange: &str, history: SessionHistory) -> Self {
+        Self {
+            product: product.to_string(),
+            exchange: exchange.to_string(),
+            history,
+        }
+    }
+    pub fn product(&self) -> &str {
+        &self.product
+    }
+    /// 交易所未知时为空字符串
+    pub fn exchange(&self) -> &str {
+        &self.exchange
+    }
+    /// 该品种的所有版本
+    pub fn history(&self) -> &SessionHistory {
+        &self.history
+    }
+    /// 最新的版本
+    pub fn session(&self) -> Option<&TradeSession> {
+        self.history.values().next_back()
+    }
+    /// 在date这一天生效的版本, 即生效日期不晚于date的最新版本,
+    /// 所有版本都在date之后才生效时返回None
+    pub fn session_on(&self, date: &MyDateType) -> Option<&TradeSession> {
+        self.history
+            .range(..=Some(*date))
+            .next_back()
+            .map(|(_, session)| session)
+    }
+}
+
 /// 如果csv文件只有两列, 则第一列为产品名, 第二列为json字符串
 /// 如果csv文件有三列, 则第一列为产品名, 第二列为交易所名, 第三列为json字符串
-pub fn load_from_read<R: Read>(read: R) -> Result<HashMap<String, TradeSession>> {
-    let mut hash = HashMap::new();
+/// 如果csv文件有四列, 则第四列为生效日期("%Y-%m-%d"或"%Y%m%d", 可以为空),
+/// 同一品种可以有多行, 分别对应不同的生效日期,
+/// csv文件中均为期货品种, 按期货规则添加开盘集合竞价, 参看TradeSession::add_futures_auctions,
+/// 返回的品种按照在csv文件中首次出现的顺序排列, 任一行有错误时返回该行的CsvRecord错误
+pub fn load_records_from_read<R: Read>(read: R) -> Result<Vec<ProductRecord>> {
+    let (records, _) = load_records(read, false)?;
+    Ok(records)
+}
+
+/// 宽松模式, 跳过有错误的行, 返回正确加载的品种及各行的错误(CsvRecord或CsvFormat),
+/// 仅在读取失败时返回错误, 参看load_records_from_read
+pub fn load_records_from_read_lenient<R: Read>(
+    read: R,
+) -> Result<(Vec<ProductRecord>, Vec<TradeSessionError>)> {
+    load_records(read, true)
+}
+
+fn load_records<R: Read>(
+    read: R,
+    lenient: bool,
+) -> Result<(Vec<ProductRecord>, Vec<TradeSessionError>)> {
+    let mut records: Vec<ProductRecord> = Vec::new();
+    let mut errors = Vec::new();
+    let mut index: HashMap<(String, String), usize> = HashMap::new();
     let mut rdr = csv::Reader::from_reader(read);
 
     for result in rdr.records() {
-        let record = result?;
-
-        let (key_idx, json_idx) = match record.len() {
-            2 => (0, 1),
-            3 => (0, 2),
-            _ => {
-                return Err(anyhow!(
-                    "bad format session: expected 2 or 3 fields, got {}, {:#?}",
-                    record.len(),
-                    record
-                ));
+        let row = result
+            .map_err(TradeSessionError::from)
+            .and_then(|record| parse_record(&record));
+        let (product, exchange, effective_from, session) = match row {
+            Ok(row) => row,
+            Err(TradeSessionError::Io(err)) => return Err(TradeSessionError::Io(err)),
+            Err(err) if lenient => {
+                errors.push(err);
+                continue;
             }
+            Err(err) => return Err(err),
         };
+        let idx = *index
+            .entry((exchange.clone(), product.clone()))
+            .or_insert_with(|| {
+                records.push(ProductRecord::new(
+                    &product,
+                    &exchange,
+                    SessionHistory::new(),
+                ));
+                records.len() - 1
+            });
+        records[idx].history.insert(effective_from, session);
+    }
 
-        let json = &record[json_idx];
-        let slices = parse_json_slices(json)?;
-        let session = TradeSession::new_from_slices(&slices);
+    Ok((records, errors))
+}
 
-        hash.insert(record[key_idx].to_string(), session);
+/// csv文件一行中(品种, 交易所, json, 生效日期)所在的列, 参看load_records_from_read
+pub(crate) fn record_layout(
+    record: &csv::StringRecord,
+) -> Result<(usize, Option<usize>, usize, Option<usize>)> {
+    match record.len() {
+        2 => Ok((0, None, 1, None)),
+        3 => Ok((0, Some(1), 2, None)),
+        4 => Ok((0, Some(1), 2, Some(3))),
+        _ => Err(TradeSessionError::CsvFormat {
+            line: record.position().map_or(0, |pos| pos.line()),
+            column: None,
+            message: format!(
+                "bad format session: expected 2, 3 or 4 fields, got {}, {:#?}",
+                record.len(),
+                record
+            ),
+        }),
     }
+}
+
+/// csv文件的一行, 返回(品种, 交易所, 生效日期, session), 错误中带有行号及品种
+fn parse_record(
+    record: &csv::StringRecord,
+) -> Result<(String, String, Option<MyDateType>, TradeSession)> {
+    let line = record.position().map_or(0, |pos| pos.line());
+    let (key_idx, exchange_idx, json_idx, date_idx) = record_layout(record)?;
 
-    Ok(hash)
+    let product = &record[key_idx];
+    let in_record = |source| TradeSessionError::CsvRecord {
+        line,
+        product: product.to_string(),
+        source: Box::new(source),
+    };
+    let json = &record[json_idx];
+    let slices = parse_json_slices(json).map_err(in_record)?;
+    let session = futures_session(&slices);
+    let effective_from = match date_idx.map(|idx| record[idx].trim()) {
+        Some(date_str) if !date_str.is_empty() => {
+            Some(parse_csv_date(date_str).map_err(in_record)?)
+        }
+        _ => None,
+    };
+    let exchange = exchange_idx.map_or("", |idx| record[idx].trim());
+    Ok((
+        product.to_string(),
+        exchange.to_string(),
+        effective_from,
+        session,
+    ))
+}
+
+/// 参看load_records_from_read, 不同交易所的品种代码相同时, 后出现的覆盖先出现的
+pub fn load_history_from_read<R: Read>(read: R) -> Result<HashMap<String, SessionHistory>> {
+    let records = load_records_from_read(read)?;
+    Ok(records
+        .into_iter()
+        .map(|record| (record.product, record.history))
+        .collect())
+}
+
+/// 参看load_history_from_read, 同一品种有多个版本时只保留最新的版本
+pub fn load_from_read<R: Read>(read: R) -> Result<HashMap<String, TradeSession>> {
+    let history = load_history_from_read(read)?;
+    Ok(latest_sessions(history))
 }
 
 /// csv文件是直接从数据库表导出的,一共三列, product,exchange,sessions
@@ -44,35 +188,103 @@ pub fn load_from_read<R: Read>(read: R) -> Result<HashMap<String, TradeSession>>
 /// ag,SHFE,"[{""Begin"":""09:00:00"",""End"":""10:15:00""},{""Begin"":""10:30:00"",""End"":""11:30:00""},{""Begin"":""13:30:00"",""End"":""15:00:00""},{""Begin"":""21:00:00"",""End"":""02:30:00""}]"
 /// 如果csv文件只有两列, 则第一列为产品名, 第二列为json字符串
 /// 如果csv文件有三列, 则第一列为产品名, 第二列为交易所名, 第三列为json字符串
+/// 如果csv文件有四列, 则第四列为生效日期, 同一品种有多个版本时只保留最新的版本
 pub fn load_from_csv<P: AsRef<Path>>(csv_file_path: P) -> Result<HashMap<String, TradeSession>> {
-    let path = csv_file_path.as_ref();
-    if !path.exists() {
-        return Err(anyhow!("file not found `{}`", path.to_string_lossy()));
-    }
-    let file = File::open(path).with_context(|| path.display().to_string())?;
-    return load_from_read(DecodeReaderBytes::new(file));
+    let history = load_history_from_csv(csv_file_path)?;
+    Ok(latest_sessions(history))
 }
 
 ///从csv文件内容加载，参数为csv文件字符串
 pub fn load_from_csv_content(csv_content: &str) -> Result<HashMap<String, TradeSession>> {
-    return load_from_read(csv_content.as_bytes());
+    load_from_read(csv_content.as_bytes())
+}
+
+/// 参看load_history_from_read
+pub fn load_history_from_csv<P: AsRef<Path>>(
+    csv_file_path: P,
+) -> Result<HashMap<String, SessionHistory>> {
+    let file = open_csv(csv_file_path.as_ref())?;
+    load_history_from_read(DecodeReaderBytes::new(file))
+}
+
+/// 参看load_records_from_read
+pub fn load_records_from_csv<P: AsRef<Path>>(csv_file_path: P) -> Result<Vec<ProductRecord>> {
+    let file = open_csv(csv_file_path.as_ref())?;
+    load_records_from_read(DecodeReaderBytes::new(file))
+}
+
+/// 宽松模式, 参看load_records_from_read_lenient
+pub fn load_records_from_csv_lenient<P: AsRef<Path>>(
+    csv_file_path: P,
+) -> Result<(Vec<ProductRecord>, Vec<TradeSessionError>)> {
+    let file = open_csv(csv_file_path.as_ref())?;
+    load_records_from_read_lenient(DecodeReaderBytes::new(file))
+}
+
+///从csv文件内容加载，参数为csv文件字符串, 参看load_records_from_read
+pub fn load_records_from_csv_content(csv_content: &str) -> Result<Vec<ProductRecord>> {
+    load_records_from_read(csv_content.as_bytes())
+}
+
+///从csv文件内容加载，参数为csv文件字符串, 参看load_history_from_read
+pub fn load_history_from_csv_content(csv_content: &str) -> Result<HashMap<String, SessionHistory>> {
+    load_history_from_read(csv_content.as_bytes())
+}
+
+/// 每个品种只保留最新的版本
+fn latest_sessions(history: HashMap<String, SessionHistory>) -> HashMap<String, TradeSession> {
+    history
+        .into_iter()
+        .filter_map(|(k, mut v)| v.pop_last().map(|(_, session)| (k, session)))
+        .collect()
 }
 
-/// product vs json_session, when these two columns loaded from database
+/// 从合约代码中提取品种代码, 即去掉合约月份及期权后缀, 大小写保持不变,
+/// ag2412 -> ag, SR501 -> SR (郑商所3位月份), IF2409 -> IF, m2501-C-3000 -> m, SR501C5000 -> SR,
+/// 带交易所前后缀的也可以, 比如 SHFE.ag2412 -> ag, ag2412.SHFE -> ag
+pub fn product_of_instrument(instrument: &str) -> &str {
+    let instrument = instrument.trim();
+    let code = instrument
+        .split('.')
+        .find(|part| part.chars().any(|c| c.is_ascii_digit()))
+        .unwrap_or(instrument);
+    let end = code
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(code.len());
+    &code[..end]
+}
+
+/// product vs json_session, when these two columns loaded from database,
+/// 同csv文件, 按期货规则添加开盘集合竞价
 pub fn load_from_json_map(
     prd_vs_json: &HashMap<String, String>,
 ) -> Result<HashMap<String, TradeSession>> {
     let mut res_map: HashMap<String, TradeSession> = HashMap::new();
     for (k, v) in prd_vs_json {
         let res_vec: Vec<SessionSlice> = parse_json_slices(v)?;
-        let session = TradeSession::new_from_slices(&res_vec);
-        res_map.insert(k.to_string(), session);
+        res_map.insert(k.to_string(), futures_session(&res_vec));
     }
     Ok(res_map)
 }
 
+/// csv文件及json中的期货品种, 按期货规则添加开盘集合竞价, 参看TradeSession::add_futures_auctions
+pub(crate) fn futures_session(slices: &Vec<SessionSlice>) -> TradeSession {
+    let mut session = TradeSession::new_from_slices(slices);
+    session.add_futures_auctions();
+    session
+}
+
 pub struct SessionManager {
+    /// 每个品种最新的版本, 不同交易所的品种代码相同时, 后加载的覆盖先加载的
     sessions: HashMap<String, TradeSession>,
+    /// (交易所, 品种) vs 品种信息, 交易所未知时为空字符串
+    records: HashMap<(String, String), ProductRecord>,
+    /// 品种 vs 交易所, 不同交易所的品种代码相同时, 后加载的覆盖先加载的
+    exchanges: HashMap<String, String>,
+    /// 交易所vs交易日历, ANY_EXCHANGE对应通用日历
+    calendars: HashMap<String, TradingCalendar>,
+    /// 交易所vs时区
+    time_zones: HashMap<String, MyTimeZone>,
 }
 impl SessionManager {
     /// 静态函数,生成一个股票交易时段
@@ -96,53 +308,199 @@ impl SessionManager {
 
     ////////////////////////////////////////////////////////////
     pub fn new() -> Self {
-        Self {
-            sessions: HashMap::new(),
-        }
+        Self::new_from_map(HashMap::new())
     }
     pub fn new_from_map(session_map: HashMap<String, TradeSession>) -> Self {
-        Self {
-            sessions: session_map,
-        }
+        let history = session_map
+            .into_iter()
+            .map(|(k, v)| (k, SessionHistory::from([(None, v)])))
+            .collect();
+        Self::new_from_history(history)
+    }
+    /// 每个品种可以有多个版本, 参看load_history_from_read, 交易所未知
+    pub fn new_from_history(history: HashMap<String, SessionHistory>) -> Self {
+        let records = history
+            .into_iter()
+            .map(|(k, v)| ProductRecord::new(&k, "", v))
+            .collect();
+        Self::new_from_records(records)
+    }
+    /// 参看load_records_from_read
+    pub fn new_from_records(records: Vec<ProductRecord>) -> Self {
+        let mut mgr = Self {
+            sessions: HashMap::new(),
+            records: HashMap::new(),
+            exchanges: HashMap::new(),
+            calendars: HashMap::new(),
+            time_zones: HashMap::new(),
+        };
+        mgr.merge_records(records);
+        mgr
     }
     /// csv file path
     pub fn new_from_csv<P: AsRef<Path>>(csv_file: P) -> Result<Self> {
-        let sessions = load_from_csv(csv_file)?;
-        Ok(Self { sessions })
+        let records = load_records_from_csv(csv_file)?;
+        Ok(Self::new_from_records(records))
     }
     pub fn new_from_csv_content(csv_content: &str) -> Result<Self> {
-        let sessions = load_from_csv_content(csv_content)?;
-        Ok(Self { sessions })
+        let records = load_records_from_csv_content(csv_content)?;
+        Ok(Self::new_from_records(records))
     }
     pub fn new_from_read<R: Read>(read: R) -> Result<Self> {
-        let sessions = load_from_read(read)?;
-        Ok(Self { sessions })
+        let records = load_records_from_read(read)?;
+        Ok(Self::new_from_records(records))
     }
     /// product vs json_session, when these two columns loaded from database
     pub fn new_from_json_map(prd_vs_json: &HashMap<String, String>) -> Result<Self> {
         let sessions = load_from_json_map(prd_vs_json)?;
-        Ok(Self { sessions })
+        Ok(Self::new_from_map(sessions))
     }
 
+    /// merge时, csv中出现的品种, 其所有版本都会被替换
     pub fn reload_csv_content(&mut self, csv_content: &str, merge: bool) -> Result<()> {
-        let sessions = load_from_csv_content(csv_content)?;
+        let records = load_records_from_csv_content(csv_content)?;
+        if !merge {
+            self.clear_products();
+        }
+        self.merge_records(records);
+        Ok(())
+    }
+    /// merge时, csv中出现的品种, 其所有版本都会被替换
+    pub fn reload_csv_file<P: AsRef<Path>>(&mut self, csv_file_path: P, merge: bool) -> Result<()> {
+        let records = load_records_from_csv(csv_file_path)?;
+        if !merge {
+            self.clear_products();
+        }
+        self.merge_records(records);
+        Ok(())
+    }
+
+    /// 宽松模式, 跳过有错误的行, 返回各行的错误, 参看load_records_from_read_lenient
+    pub fn new_from_csv_lenient<P: AsRef<Path>>(
+        csv_file: P,
+    ) -> Result<(Self, Vec<TradeSessionError>)> {
+        let (records, errors) = load_records_from_csv_lenient(csv_file)?;
+        Ok((Self::new_from_records(records), errors))
+    }
+    /// 宽松模式, 跳过有错误的行, 返回各行的错误, merge时只出现在错误行的品种保留原有的数据
+    pub fn reload_csv_content_lenient(
+        &mut self,
+        csv_content: &str,
+        merge: bool,
+    ) -> Result<Vec<TradeSessionError>> {
+        let (records, errors) = load_records_from_read_lenient(csv_content.as_bytes())?;
+        if !merge {
+            self.clear_products();
+        }
+        self.merge_records(records);
+        Ok(errors)
+    }
+    /// 宽松模式, 参看reload_csv_content_lenient
+    pub fn reload_csv_file_lenient<P: AsRef<Path>>(
+        &mut self,
+        csv_file_path: P,
+        merge: bool,
+    ) -> Result<Vec<TradeSessionError>> {
+        let (records, errors) = load_records_from_csv_lenient(csv_file_path)?;
+        if !merge {
+            self.clear_products();
+        }
+        self.merge_records(records);
+        Ok(errors)
+    }
+
+    fn clear_products(&mut self) {
+        self.sessions.clear();
+        self.records.clear();
+        self.exchanges.clear();
+    }
+
+    fn merge_records(&mut self, records: Vec<ProductRecord>) {
+        for record in records {
+            self.add_record(record);
+        }
+    }
+
+    /// 如果该交易所该品种的旧数据存在，会被替换, 没有任何版本的品种会被忽略,
+    /// 交易所设置了时区时, 所有版本都使用该时区
+    pub fn add_record(&mut self, mut record: ProductRecord) {
+        if let Some(tz) = self.time_zones.get(&record.exchange) {
+            for session in record.history.values_mut() {
+                session.set_time_zone(tz);
+            }
+        }
+        let Some(latest) = record.session() else {
+            return;
+        };
+        self.sessions.insert(record.product.clone(), latest.clone());
+        self.exchanges
+            .insert(record.product.clone(), record.exchange.clone());
+        self.records
+            .insert((record.exchange.clone(), record.product.clone()), record);
+    }
+
+    /// 节假日csv文件内容, 参看load_calendars_from_read
+    pub fn reload_calendar_csv_content(&mut self, csv_content: &str, merge: bool) -> Result<()> {
+        let calendars = load_calendars_from_csv_content(csv_content)?;
         if merge {
-            self.sessions.extend(sessions);
+            self.calendars.extend(calendars);
         } else {
-            self.sessions = sessions;
+            self.calendars = calendars;
         }
         Ok(())
     }
-    pub fn reload_csv_file<P: AsRef<Path>>(&mut self, csv_file_path: P, merge: bool) -> Result<()> {
-        let sessions = load_from_csv(csv_file_path)?;
+    /// 节假日csv文件, 参看load_calendars_from_read
+    pub fn reload_calendar_csv_file<P: AsRef<Path>>(
+        &mut self,
+        csv_file_path: P,
+        merge: bool,
+    ) -> Result<()> {
+        let calendars = load_calendars_from_csv(csv_file_path)?;
         if merge {
-            self.sessions.extend(sessions);
+            self.calendars.extend(calendars);
         } else {
-            self.sessions = sessions;
+            self.calendars = calendars;
         }
         Ok(())
     }
 
+    /// 如果旧数据存在，会被替换, exchange为ANY_EXCHANGE时设置通用日历
+    pub fn set_calendar(&mut self, exchange: &str, calendar: TradingCalendar) {
+        self.calendars.insert(exchange.to_string(), calendar);
+    }
+
+    /// 交易所的日历, 没有专门设置时返回通用日历, 都没有则返回None
+    pub fn calendar(&self, exchange: &str) -> Option<&TradingCalendar> {
+        self.calendars
+            .get(exchange)
+            .or_else(|| self.calendars.get(ANY_EXCHANGE))
+    }
+
+    /// 设置交易所的时区, 该交易所已加载及之后加载的品种都使用此时区, 参看TradeSession::set_time_zone
+    pub fn set_time_zone(&mut self, exchange: &str, tz: &MyTimeZone) {
+        let records = self
+            .records
+            .iter_mut()
+            .filter(|((ex, _), _)| ex == exchange);
+        for ((ex, product), record) in records {
+            for session in record.history.values_mut() {
+                session.set_time_zone(tz);
+            }
+            // 同名品种后加载的在其他交易所时, 不改变session_map
+            if self.exchanges.get(product) == Some(ex)
+                && let Some(latest) = record.session()
+            {
+                self.sessions.insert(product.clone(), latest.clone());
+            }
+        }
+        self.time_zones.insert(exchange.to_string(), tz.to_owned());
+    }
+
+    /// 交易所的时区, 没有设置时返回None
+    pub fn time_zone(&self, exchange: &str) -> Option<&MyTimeZone> {
+        self.time_zones.get(exchange)
+    }
+
     pub fn session_map(&self) -> &HashMap<String, TradeSession> {
         &self.sessions
     }
@@ -155,8 +513,153 @@ impl SessionManager {
         self.sessions.get(product)
     }
 
+    /// 合约代码对应的品种代码(即session_map中的key), 参看product_of_instrument,
+    /// 找不到时依次尝试大写(郑商所、中金所)和小写(上期所、大商所), 都找不到时返回None
+    pub fn resolve_product(&self, instrument: &str) -> Option<&str> {
+        let product = product_of_instrument(instrument);
+        if product.is_empty() {
+            return None;
+        }
+        [
+            product.to_string(),
+            product.to_ascii_uppercase(),
+            product.to_ascii_lowercase(),
+        ]
+        .iter()
+        .find_map(|key| self.sessions.get_key_value(key.as_str()))
+        .map(|(key, _)| key.as_str())
+    }
+
+    /// 用合约代码获取session, 比如ag2412, SR501, m2501-C-3000, 参看resolve_product
+    pub fn get_session_for_instrument(&self, instrument: &str) -> Option<&TradeSession> {
+        self.resolve_product(instrument)
+            .and_then(|product| self.sessions.get(product))
+    }
+
+    /// 同get_session, 品种不存在时返回UnknownProduct错误
+    pub fn try_get_session(&self, product: &str) -> Result<&TradeSession> {
+        self.get_session(product)
+            .ok_or_else(|| TradeSessionError::UnknownProduct(product.to_string()))
+    }
+
+    /// 同get_session_for_instrument, 品种不存在时返回UnknownProduct错误, 参数为合约代码
+    pub fn try_get_session_for_instrument(&self, instrument: &str) -> Result<&TradeSession> {
+        self.get_session_for_instrument(instrument)
+            .ok_or_else(|| TradeSessionError::UnknownProduct(instrument.to_string()))
+    }
+
+    /// 如果旧数据存在，会被替换, 包括所有的历史版本, 所属交易所保持不变
     pub fn add_session(&mut self, product: &str, session: TradeSession) {
-        self.sessions.insert(product.to_string(), session);
+        let exchange = self.exchange_of(product).unwrap_or("").to_string();
+        let history = SessionHistory::from([(None, session)]);
+        self.add_record(ProductRecord::new(product, &exchange, history));
+    }
+
+    /// 添加一个从effective_from(含)开始生效的版本, 同一生效日期的旧版本会被替换
+    pub fn add_session_on(
+        &mut self,
+        product: &str,
+        effective_from: MyDateType,
+        session: TradeSession,
+    ) {
+        let mut record = self
+            .record(product)
+            .cloned()
+            .unwrap_or_else(|| ProductRecord::new(product, "", SessionHistory::new()));
+        record.history.insert(Some(effective_from), session);
+        self.add_record(record);
+    }
+
+    /// 品种信息, 不同交易所的品种代码相同时, 返回后加载的
+    pub fn record(&self, product: &str) -> Option<&ProductRecord> {
+        let exchange = self.exchanges.get(product)?;
+        self.records.get(&(exchange.clone(), product.to_string()))
+    }
+
+    /// 用(交易所, 品种)查找品种信息, 交易所未知的品种, exchange为空字符串
+    pub fn record_on(&self, exchange: &str, product: &str) -> Option<&ProductRecord> {
+        self.records
+            .get(&(exchange.to_string(), product.to_string()))
+    }
+
+    /// 所有品种信息, 顺序不定
+    pub fn records(&self) -> impl Iterator<Item = &ProductRecord> {
+        self.records.values()
+    }
+
+    /// 用(交易所, 品种)查找最新的版本
+    pub fn get_session_on_exchange(&self, exchange: &str, product: &str) -> Option<&TradeSession> {
+        self.record_on(exchange, product)
+            .and_then(|record| record.session())
+    }
+
+    /// 品种所属的交易所, 品种不存在或者交易所未知时返回None
+    pub fn exchange_of(&self, product: &str) -> Option<&str> {
+        self.exchanges
+            .get(product)
+            .map(|exchange| exchange.as_str())
+            .filter(|exchange| !exchange.is_empty())
+    }
+
+    /// 该交易所的所有品种, 按品种代码排序
+    pub fn products_on(&self, exchange: &str) -> Vec<&str> {
+        let mut products: Vec<&str> = self
+            .records
+            .keys()
+            .filter(|(ex, _)| ex == exchange)
+            .map(|(_, product)| product.as_str())
+            .collect();
+        products.sort_unstable();
+        products
+    }
+
+    /// 多个品种的时段的并集, 比如要保持CTP连接的时段, 不含集合竞价时段,
+    /// 结果使用第一个品种的anchor及时区, 任一品种不存在时返回None, products为空时返回空的session
+    pub fn union_of(&self, products: &[&str]) -> Option<TradeSession> {
+        self.fold_sessions(products, TradeSession::union)
+    }
+
+    /// 多个品种的时段的交集, 比如价差合约各条腿同时开市的时段, 参看union_of
+    pub fn intersection_of(&self, products: &[&str]) -> Option<TradeSession> {
+        self.fold_sessions(products, TradeSession::intersection)
+    }
+
+    fn fold_sessions<F>(&self, products: &[&str], op: F) -> Option<TradeSession>
+    where
+        F: Fn(&TradeSession, &TradeSession) -> TradeSession,
+    {
+        let Some((first, rest)) = products.split_first() else {
+            return Some(TradeSession::new());
+        };
+        let first = self.sessions.get(*first)?;
+        // 与自身运算, 得到规范化的结果(不含集合竞价时段)
+        let mut acc = op(first, first);
+        for product in rest {
+            acc = op(&acc, self.sessions.get(*product)?);
+        }
+        Some(acc)
+    }
+
+    /// 该品种的所有版本
+    pub fn session_history(&self, product: &str) -> Option<&SessionHistory> {
+        self.record(product).map(|record| record.history())
+    }
+
+    /// 在date这一天生效的版本, 即生效日期不晚于date的最新版本,
+    /// 所有版本都在date之后才生效时返回None
+    pub fn get_session_on(&self, product: &str, date: &MyDateType) -> Option<&TradeSession> {
+        self.record(product)?.session_on(date)
+    }
+
+    /// 品种所属交易所的日历, 交易所未知时使用通用日历
+    fn product_calendar(&self, product: &str) -> Option<&TradingCalendar> {
+        self.calendar(self.exchange_of(product).unwrap_or(ANY_EXCHANGE))
+    }
+
+    /// dt所属的交易日生效的版本
+    fn session_at(&self, product: &str, dt: &MyDateTimeType) -> Option<&TradeSession> {
+        let trading_day = self.trading_day(product, dt)?;
+        self.get_session_on(product, &trading_day)
     }
 
     /// 该品种日线开始时间，9:00/9:15/9:30/21:00, 一般是集合竞价所在的时间
@@ -184,6 +687,209 @@ impl SessionManager {
             .map(|s| s.in_session(ts, include_begin, include_end))
     }
 
+    /// 一个时间点所处的交易阶段, 参看TradeSession::phase_at
+    pub fn phase_at(&self, product: &str, ts: &MyTimeType) -> Option<TradingPhase> {
+        self.sessions.get(product).map(|s| s.phase_at(ts))
+    }
+
+    /// 一个日期时间点所处的交易阶段, 休市的日期为Closed, 使用品种所属交易所的日历
+    pub fn phase_at_datetime(&self, product: &str, dt: &MyDateTimeType) -> Option<TradingPhase> {
+        let calendar = self.product_calendar(product);
+        self.session_at(product, dt)
+            .map(|s| s.phase_at_datetime(dt, calendar))
+    }
+
+    /// 一个日期时间点, 按交易日历可以交易吗? 排除周末、节假日及节假日前的夜盘,
+    /// 使用品种所属交易所的日历
+    pub fn is_tradable(&self, product: &str, dt: &MyDateTimeType) -> Option<bool> {
+        let calendar = self.product_calendar(product);
+        self.session_at(product, dt)
+            .map(|s| s.is_tradable(dt, calendar))
+    }
+
+    /// 一个日期时间点, 在时段内吗? 排除周末、节假日及节假日前的夜盘,
+    /// 使用品种所属交易所的日历
+    pub fn in_session_at(
+        &self,
+        product: &str,
+        dt: &MyDateTimeType,
+        include_begin: bool,
+        include_end: bool,
+    ) -> Option<bool> {
+        let calendar = self.product_calendar(product);
+        self.session_at(product, dt)
+            .map(|s| s.in_session_at(dt, include_begin, include_end, calendar))
+    }
+
+    /// start, end之间任意日期时间点落在session中吗? 排除周末、节假日及节假日前的夜盘,
+    /// 跨越版本生效日期时, 各段使用各自的版本, 参看split_by_version
+    pub fn any_in_session_between(
+        &self,
+        product: &str,
+        start: &MyDateTimeType,
+        end: &MyDateTimeType,
+        include_begin_end: bool,
+    ) -> Option<bool> {
+        let calendar = self.product_calendar(product);
+        let pieces = self.split_by_version(product, start, end)?;
+        Some(pieces.iter().any(|(begin, finish, s)| {
+            s.any_in_session_between(begin, finish, include_begin_end, calendar)
+        }))
+    }
+
+    /// dt所在时段的实际起止时间, 含开始，不含结束
+    pub fn current_slice(
+        &self,
+        product: &str,
+        dt: &MyDateTimeType,
+    ) -> Option<(MyDateTimeType, MyDateTimeType)> {
+        let calendar = self.product_calendar(product);
+        self.session_at(product, dt)
+            .and_then(|s| s.current_slice(dt, calendar))
+    }
+
+    /// dt之后(不含dt)的下一个时段开始时间, 跨越夜盘零点、周末及节假日
+    pub fn next_open(&self, product: &str, dt: &MyDateTimeType) -> Option<MyDateTimeType> {
+        let calendar = self.product_calendar(product);
+        self.session_at(product, dt)
+            .and_then(|s| s.next_open(dt, calendar))
+    }
+
+    /// dt之后(不含dt)的下一个时段结束时间
+    pub fn next_close(&self, product: &str, dt: &MyDateTimeType) -> Option<MyDateTimeType> {
+        let calendar = self.product_calendar(product);
+        self.session_at(product, dt)
+            .and_then(|s| s.next_close(dt, calendar))
+    }
+
+    /// dt之前(含dt)的最近一个时段结束时间
+    pub fn prev_close(&self, product: &str, dt: &MyDateTimeType) -> Option<MyDateTimeType> {
+        let calendar = self.product_calendar(product);
+        self.session_at(product, dt)
+            .and_then(|s| s.prev_close(dt, calendar))
+    }
+
+    /// 距离当前时段结束还有多长时间, 不在时段内时返回None
+    pub fn time_to_slice_end(&self, product: &str, dt: &MyDateTimeType) -> Option<Duration> {
+        let calendar = self.product_calendar(product);
+        self.session_at(product, dt)
+            .and_then(|s| s.time_to_slice_end(dt, calendar))
+    }
+
+    /// 距离dt所属交易日的收盘还有多长时间, 已经收盘时返回None
+    pub fn time_to_day_end(&self, product: &str, dt: &MyDateTimeType) -> Option<Duration> {
+        let calendar = self.product_calendar(product);
+        self.session_at(product, dt)
+            .and_then(|s| s.time_to_day_end(dt, calendar))
+    }
+
+    /// start到end之间的交易时长, 跳过盘中休息、周末及节假日, 使用品种所属交易所的日历,
+    /// 跨越版本生效日期时, 各段按各自的版本计算之后相加, 参看split_by_version
+    pub fn trading_duration_between(
+        &self,
+        product: &str,
+        start: &MyDateTimeType,
+        end: &MyDateTimeType,
+    ) -> Option<Duration> {
+        let calendar = self.product_calendar(product);
+        let pieces = self.split_by_version(product, start, end)?;
+        Some(
+            pieces
+                .iter()
+                .map(|(begin, finish, s)| s.trading_duration_between(begin, finish, calendar))
+                .sum(),
+        )
+    }
+
+    /// 从start开始经过duration交易时长之后的日期时间点, 跳过盘中休息、周末及节假日,
+    /// 使用品种所属交易所的日历, 跨越版本生效日期时, 之后的交易时长按新版本计算
+    pub fn add_trading_time(
+        &self,
+        product: &str,
+        start: &MyDateTimeType,
+        duration: Duration,
+    ) -> Option<MyDateTimeType> {
+        let calendar = self.product_calendar(product);
+        let versions = self.versions_from(product, start)?;
+        let mut remaining = duration;
+        for (idx, (begin, s)) in versions.iter().enumerate() {
+            if let Some((next, _)) = versions.get(idx + 1) {
+                let available = s.trading_duration_between(begin, next, calendar);
+                if remaining > available {
+                    remaining -= available;
+                    continue;
+                }
+            }
+            return s.add_trading_time(begin, remaining, calendar);
+        }
+        None
+    }
+
+    /// start之后各版本开始生效的时间点及该版本, 第一项为start及start所属交易日生效的版本,
+    /// 生效日期对应的交易日从前一个交易日之后的anchor(比如20:00)开始, 同trading_day
+    fn versions_from(
+        &self,
+        product: &str,
+        start: &MyDateTimeType,
+    ) -> Option<Vec<(MyDateTimeType, &TradeSession)>> {
+        let default = TradingCalendar::new();
+        let calendar = self.product_calendar(product).unwrap_or(&default);
+        let mut versions = vec![(*start, self.session_at(product, start)?)];
+        for (date, session) in self.record(product)?.history() {
+            let Some(date) = date else {
+                continue;
+            };
+            let prev = calendar.prev_trading_day(date);
+            let begin = if session.anchor_seconds() == 0 {
+                make_datetime(&tomorrow(&prev), &session.anchor())
+            } else {
+                make_datetime(&prev, &session.anchor())
+            };
+            if begin > *start {
+                versions.push((begin, session));
+            }
+        }
+        Some(versions)
+    }
+
+    /// 按版本生效的时间点把start到end切分为多段, 返回各段的起止时间及生效的版本, 参看versions_from
+    fn split_by_version(
+        &self,
+        product: &str,
+        start: &MyDateTimeType,
+        end: &MyDateTimeType,
+    ) -> Option<Vec<(MyDateTimeType, MyDateTimeType, &TradeSession)>> {
+        let versions = self.versions_from(product, start)?;
+        let mut pieces = vec![];
+        for (idx, (begin, session)) in versions.iter().enumerate() {
+            if idx > 0 && begin >= end {
+                break;
+            }
+            let finish = versions
+                .get(idx + 1)
+                .map_or(*end, |(next, _)| (*next).min(*end));
+            pieces.push((*begin, finish, *session));
+        }
+        Some(pieces)
+    }
+
+    /// 日期时间点所属的交易日, 比如周五22:30及周六00:30都属于下周一,
+    /// 使用品种所属交易所的日历, 先用dt当天生效的版本(没有时用最早的版本)求出交易日,
+    /// 再用该交易日生效的版本重新求一次, 版本之间anchor不同时也使用当时的anchor
+    pub fn trading_day(&self, product: &str, dt: &MyDateTimeType) -> Option<MyDateType> {
+        let calendar = self.product_calendar(product);
+        let record = self.record(product)?;
+        let first = record
+            .session_on(&dt.date())
+            .or_else(|| record.history().values().next())?;
+        let day = first.trading_day(dt, calendar);
+        Some(
+            record
+                .session_on(&day)
+                .map_or(day, |s| s.trading_day(dt, calendar)),
+        )
+    }
+
     pub fn any_in_session(
         &self,
         product: &str,
@@ -195,12 +901,58 @@ impl SessionManager {
             .get(product)
             .map(|s| s.any_in_session(start, end, include_begin_end))
     }
+
+    /// 一个时间点, 按交易日历可以交易吗? 按品种所属交易所的时区转为当地时间, 参看is_tradable
+    pub fn is_tradable_zoned(&self, product: &str, zoned: &MyZonedType) -> Option<bool> {
+        let calendar = self.product_calendar(product);
+        self.session_at_zoned(product, zoned)
+            .map(|s| s.is_tradable_zoned(zoned, calendar))
+    }
+
+    /// 一个时间点所处的交易阶段, 按品种所属交易所的时区转为当地时间, 参看phase_at_datetime
+    pub fn phase_at_zoned(&self, product: &str, zoned: &MyZonedType) -> Option<TradingPhase> {
+        let calendar = self.product_calendar(product);
+        self.session_at_zoned(product, zoned)
+            .map(|s| s.phase_at_zoned(zoned, calendar))
+    }
+
+    /// 一个时间点所属的交易日, 按品种所属交易所的时区转为当地时间, 参看trading_day
+    pub fn trading_day_zoned(&self, product: &str, zoned: &MyZonedType) -> Option<MyDateType> {
+        let dt = self.sessions.get(product)?.local_datetime(zoned);
+        self.trading_day(product, &dt)
+    }
+
+    /// 时间点之后的下一个时段开始时间点, 使用品种所属交易所的时区, 参看next_open
+    pub fn next_open_zoned(&self, product: &str, zoned: &MyZonedType) -> Option<MyZonedType> {
+        let calendar = self.product_calendar(product);
+        self.session_at_zoned(product, zoned)
+            .and_then(|s| s.next_open_zoned(zoned, calendar))
+    }
+
+    /// 时间点之后的下一个时段结束时间点, 使用品种所属交易所的时区, 参看next_close
+    pub fn next_close_zoned(&self, product: &str, zoned: &MyZonedType) -> Option<MyZonedType> {
+        let calendar = self.product_calendar(product);
+        self.session_at_zoned(product, zoned)
+            .and_then(|s| s.next_close_zoned(zoned, calendar))
+    }
+
+    /// 时间点所属的交易日生效的版本
+    fn session_at_zoned(&self, product: &str, zoned: &MyZonedType) -> Option<&TradeSession> {
+        let dt = self.sessions.get(product)?.local_datetime(zoned);
+        self.session_at(product, &dt)
+    }
+}
+
+impl Default for SessionManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::jcswitch::make_time;
+    use crate::jcswitch::{date_at_hms, make_date, make_time};
 
     use super::*;
 
@@ -219,15 +971,437 @@ mod tests {
         println!("ag day begin: {}", day_begin);
         let day_end = s_mgr.day_end("ag").unwrap();
         println!("ag day end: {}", day_end);
-        s_mgr
-            .in_session("ag", &make_time(9, 0, 0), true, false)
-            .map(|in_session| println!("ag in session at 09:00:00: {}", in_session));
-        s_mgr
-            .in_session("ag", &make_time(1, 15, 0), true, false)
-            .map(|in_session| println!("ag in session at 1:15:00: {}", in_session));
-        s_mgr
-            .in_session("ag", &make_time(16, 0, 0), true, false)
-            .map(|in_session| println!("ag in session at 16:00:00: {}", in_session));
+        if let Some(in_session) = s_mgr.in_session("ag", &make_time(9, 0, 0), true, false) {
+            println!("ag in session at 09:00:00: {}", in_session);
+        }
+        if let Some(in_session) = s_mgr.in_session("ag", &make_time(1, 15, 0), true, false) {
+            println!("ag in session at 1:15:00: {}", in_session);
+        }
+        if let Some(in_session) = s_mgr.in_session("ag", &make_time(16, 0, 0), true, false) {
+            println!("ag in session at 16:00:00: {}", in_session);
+        }
+
+        // 从json加载时同样有集合竞价
+        let json = r#"[{"Begin":"21:00","End":"02:30"},{"Begin":"09:00","End":"10:15"}]"#;
+        let json_mgr =
+            SessionManager::new_from_json_map(&HashMap::from([("ag".to_string(), json.into())]))?;
+        assert_eq!(
+            json_mgr.get_session("ag").unwrap().auctions(),
+            session.auctions()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn lenient_csv() -> anyhow::Result<()> {
+        let csv = r#"product,exchange,session
+ag,SHFE,"[{""Begin"":""09:00"",""End"":""10:15""}]"
+CF,CZCE,"[{""Begin"":""09:00"",""End"":""10:15""}"
+IF,CFFEX,"[{""Begin"":""09:30"",""End"":""9点半""}]"
+rb,SHFE,"[{""Begin"":""09:00"",""End"":""10:15""}]"
+"#;
+        // 严格模式, 第一个错误的行号及品种
+        match SessionManager::new_from_csv_content(csv) {
+            Err(TradeSessionError::CsvRecord {
+                line,
+                product,
+                source,
+            }) => {
+                assert_eq!((line, product.as_str()), (3, "CF"));
+                assert!(matches!(*source, TradeSessionError::JsonFormat(_)));
+            }
+            other => panic!("expect CsvRecord, got {:?}", other.map(|_| ())),
+        }
+
+        // 宽松模式, 加载正确的行
+        let (records, errors) = load_records_from_read_lenient(csv.as_bytes())?;
+        let products: Vec<&str> = records.iter().map(|r| r.product()).collect();
+        assert_eq!(products, vec!["ag", "rb"]);
+        assert_eq!(errors.len(), 2);
+        assert!(
+            errors[1]
+                .to_string()
+                .starts_with("csv line 4, product `IF`: ")
+        );
+
+        let mut s_mgr = SessionManager::new_from_csv_content(include_str!("../tradesession.csv"))?;
+        let errors = s_mgr.reload_csv_content_lenient(csv, true)?;
+        assert_eq!(errors.len(), 2);
+        assert!(s_mgr.get_session("ag").unwrap().get_slices().len() == 1);
+        // CF只出现在错误的行, 保留原有的数据
+        assert!(s_mgr.get_session("CF").unwrap().has_night());
+        Ok(())
+    }
+
+    #[test]
+    fn tradable_with_calendar() -> anyhow::Result<()> {
+        let mut s_mgr = SessionManager::new_from_csv_content(include_str!("../tradesession.csv"))?;
+        // 没有日历时仅排除周末
+        let sat = make_date(2025, 7, 26);
+        assert_eq!(
+            s_mgr.is_tradable("ag", &date_at_hms(&sat, 10, 0, 0)),
+            Some(false)
+        );
+        assert_eq!(
+            s_mgr.is_tradable("ag", &date_at_hms(&sat, 1, 0, 0)),
+            Some(true)
+        );
+        assert_eq!(s_mgr.is_tradable("XX", &date_at_hms(&sat, 1, 0, 0)), None);
+
+        s_mgr.reload_calendar_csv_content(include_str!("../holidays.csv"), false)?;
+        // 周五夜盘跨零点到周六2:30
+        let fri = make_date(2025, 7, 25);
+        assert_eq!(
+            s_mgr.is_tradable("ag", &date_at_hms(&fri, 22, 30, 0)),
+            Some(true)
+        );
+        assert_eq!(
+            s_mgr.is_tradable("ag", &date_at_hms(&sat, 2, 0, 0)),
+            Some(true)
+        );
+        assert_eq!(
+            s_mgr.is_tradable("ag", &date_at_hms(&sat, 3, 0, 0)),
+            Some(false)
+        );
+        // 周日晚上、周一凌晨没有夜盘
+        let sun = make_date(2025, 7, 27);
+        let mon = make_date(2025, 7, 28);
+        assert_eq!(
+            s_mgr.is_tradable("ag", &date_at_hms(&sun, 21, 30, 0)),
+            Some(false)
+        );
+        assert_eq!(
+            s_mgr.is_tradable("ag", &date_at_hms(&mon, 1, 0, 0)),
+            Some(false)
+        );
+        assert_eq!(
+            s_mgr.is_tradable("ag", &date_at_hms(&mon, 9, 30, 0)),
+            Some(true)
+        );
+
+        // 春节前最后一个交易日, 白天可以交易, 晚上没有夜盘
+        let eve = make_date(2025, 1, 27);
+        assert_eq!(
+            s_mgr.is_tradable("ag", &date_at_hms(&eve, 14, 0, 0)),
+            Some(true)
+        );
+        assert_eq!(
+            s_mgr.is_tradable("ag", &date_at_hms(&eve, 21, 30, 0)),
+            Some(false)
+        );
+        assert_eq!(
+            s_mgr.is_tradable("ag", &date_at_hms(&make_date(2025, 1, 28), 1, 0, 0)),
+            Some(false)
+        );
+        assert_eq!(
+            s_mgr.is_tradable("ag", &date_at_hms(&make_date(2025, 1, 28), 10, 0, 0)),
+            Some(false)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn trading_day_of_datetime() -> anyhow::Result<()> {
+        let mut s_mgr = SessionManager::new_from_csv_content(include_str!("../tradesession.csv"))?;
+        s_mgr.reload_calendar_csv_content(include_str!("../holidays.csv"), false)?;
+
+        let fri = make_date(2025, 7, 25);
+        let sat = make_date(2025, 7, 26);
+        let mon = make_date(2025, 7, 28);
+        let tue = make_date(2025, 7, 29);
+        let trading_day = |dt| s_mgr.trading_day("ag", &dt).unwrap();
+        assert_eq!(trading_day(date_at_hms(&fri, 10, 0, 0)), fri);
+        assert_eq!(trading_day(date_at_hms(&fri, 19, 59, 59)), fri);
+        assert_eq!(trading_day(date_at_hms(&fri, 20, 0, 0)), mon);
+        assert_eq!(trading_day(date_at_hms(&fri, 22, 30, 0)), mon);
+        assert_eq!(trading_day(date_at_hms(&sat, 0, 30, 0)), mon);
+        assert_eq!(trading_day(date_at_hms(&sat, 10, 0, 0)), mon);
+        assert_eq!(trading_day(date_at_hms(&mon, 22, 0, 0)), tue);
+        assert_eq!(trading_day(date_at_hms(&tue, 1, 0, 0)), tue);
+
+        // 春节前最后一个交易日晚上, 属于节后第一个交易日
+        let eve = make_date(2025, 1, 27);
+        assert_eq!(
+            trading_day(date_at_hms(&eve, 21, 0, 0)),
+            make_date(2025, 2, 5)
+        );
+        assert_eq!(s_mgr.trading_day("XX", &date_at_hms(&eve, 21, 0, 0)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn in_session_between_datetimes() -> anyhow::Result<()> {
+        let mut s_mgr = SessionManager::new_from_csv_content(include_str!("../tradesession.csv"))?;
+        s_mgr.reload_calendar_csv_content(include_str!("../holidays.csv"), false)?;
+
+        let eve = make_date(2025, 1, 27);
+        let at = |hour, minute| date_at_hms(&eve, hour, minute, 0);
+        assert_eq!(
+            s_mgr.in_session_at("ag", &at(15, 0), true, true),
+            Some(true)
+        );
+        assert_eq!(
+            s_mgr.in_session_at("ag", &at(15, 0), true, false),
+            Some(false)
+        );
+        // 春节前最后一个交易日的收盘之后, 直到节后第一个交易日的夜盘开始, 都没有交易
+        let reopen = date_at_hms(&make_date(2025, 2, 5), 9, 0, 0);
+        let night = date_at_hms(&make_date(2025, 2, 5), 21, 0, 0);
+        assert_eq!(
+            s_mgr.any_in_session_between("ag", &at(15, 0), &reopen, false),
+            Some(false)
+        );
+        assert_eq!(
+            s_mgr.any_in_session_between("ag", &at(15, 0), &reopen, true),
+            Some(true)
+        );
+        assert_eq!(
+            s_mgr.any_in_session_between("ag", &at(16, 0), &night, false),
+            Some(true)
+        );
+        assert_eq!(
+            s_mgr.any_in_session_between("XX", &at(16, 0), &night, false),
+            None
+        );
+        assert_eq!(s_mgr.next_open("ag", &at(15, 0)), Some(reopen));
+        assert_eq!(s_mgr.prev_close("ag", &reopen), Some(at(15, 0)));
+        Ok(())
+    }
+
+    #[test]
+    fn session_history_by_date() -> anyhow::Result<()> {
+        // 股指期货2016-01-01起收盘时间由15:15改为15:00
+        let csv_str = r#""product","exchange","sessions","effective_from"
+IF,CFFEX,"[{""Begin"":""09:15:00"",""End"":""11:30:00""},{""Begin"":""13:00:00"",""End"":""15:15:00""}]",
+IF,CFFEX,"[{""Begin"":""09:30:00"",""End"":""11:30:00""},{""Begin"":""13:00:00"",""End"":""15:00:00""}]",2016-01-01
+"#;
+        let mut s_mgr = SessionManager::new_from_csv_content(csv_str)?;
+        assert_eq!(s_mgr.session_history("IF").unwrap().len(), 2);
+        assert_eq!(s_mgr.day_end("IF"), Some(&make_time(15, 0, 0)));
+
+        let old = s_mgr
+            .get_session_on("IF", &make_date(2015, 12, 31))
+            .unwrap();
+        assert_eq!(old.day_end(), &make_time(15, 15, 0));
+        let new = s_mgr.get_session_on("IF", &make_date(2016, 1, 4)).unwrap();
+        assert_eq!(new.day_end(), &make_time(15, 0, 0));
+
+        let old_day = make_date(2015, 12, 31);
+        let new_day = make_date(2016, 1, 4);
+        assert_eq!(
+            s_mgr.in_session_at("IF", &date_at_hms(&old_day, 15, 10, 0), true, false),
+            Some(true)
+        );
+        assert_eq!(
+            s_mgr.in_session_at("IF", &date_at_hms(&new_day, 15, 10, 0), true, false),
+            Some(false)
+        );
+        // 跨越生效日期: 2015-12-31为270分钟, 2016-01-01(没有加载假日)及2016-01-04各240分钟
+        let start = date_at_hms(&old_day, 9, 15, 0);
+        let end = date_at_hms(&new_day, 15, 15, 0);
+        let minutes = |n: u64| Duration::from_secs(n * 60);
+        assert_eq!(
+            s_mgr.trading_duration_between("IF", &start, &end),
+            Some(minutes(270 + 240 * 2))
+        );
+        assert_eq!(
+            s_mgr.add_trading_time("IF", &start, minutes(270 + 240 * 2)),
+            Some(date_at_hms(&new_day, 15, 0, 0))
+        );
+        assert_eq!(
+            s_mgr.add_trading_time("IF", &start, minutes(270 + 30)),
+            Some(date_at_hms(&make_date(2016, 1, 1), 10, 0, 0))
+        );
+        assert_eq!(
+            s_mgr.add_trading_time("IF", &start, minutes(60)),
+            Some(date_at_hms(&old_day, 10, 15, 0))
+        );
+        let closed = date_at_hms(&new_day, 15, 5, 0);
+        assert_eq!(
+            s_mgr.any_in_session_between("IF", &start, &closed, false),
+            Some(true)
+        );
+        assert_eq!(
+            s_mgr.any_in_session_between("IF", &closed, &end, false),
+            Some(false)
+        );
+
+        // 只有带生效日期的版本时, 更早的日期找不到
+        s_mgr.add_session("IF", TradeSession::new_stock_index_session());
+        assert_eq!(s_mgr.session_history("IF").unwrap().len(), 1);
+        s_mgr.add_session_on("IC", new_day, TradeSession::new_stock_index_session());
+        assert!(s_mgr.get_session_on("IC", &old_day).is_none());
+        assert!(s_mgr.get_session_on("IC", &new_day).is_some());
+        assert_eq!(
+            s_mgr.in_session_at("IC", &date_at_hms(&old_day, 10, 0, 0), true, false),
+            None
+        );
+
+        // 交易日按当时生效的版本计算: 2025-07-01之前以0:00为交易日开始, 晚上属于当天
+        let mut midnight = TradeSession::new_with_anchor(&make_time(0, 0, 0));
+        midnight.add_slice(9, 0, 15, 0)?;
+        midnight.post_fix();
+        s_mgr.add_session("XX", midnight);
+        s_mgr.add_session_on(
+            "XX",
+            make_date(2025, 7, 1),
+            TradeSession::new_commodity_session_night(),
+        );
+        let fri = make_date(2025, 6, 27);
+        assert_eq!(
+            s_mgr.trading_day("XX", &date_at_hms(&fri, 21, 30, 0)),
+            Some(fri)
+        );
+        let mon = make_date(2025, 6, 30);
+        assert_eq!(
+            s_mgr.trading_day("XX", &date_at_hms(&mon, 21, 30, 0)),
+            Some(mon)
+        );
+        let tue = make_date(2025, 7, 1);
+        assert_eq!(
+            s_mgr.trading_day("XX", &date_at_hms(&tue, 21, 30, 0)),
+            Some(make_date(2025, 7, 2))
+        );
+        assert_eq!(
+            s_mgr.in_session_at("XX", &date_at_hms(&fri, 21, 30, 0), true, false),
+            Some(false)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn instrument_to_product() -> anyhow::Result<()> {
+        assert_eq!(product_of_instrument("ag2412"), "ag");
+        assert_eq!(product_of_instrument("SR501"), "SR");
+        assert_eq!(product_of_instrument("IF2409"), "IF");
+        assert_eq!(product_of_instrument("m2501-C-3000"), "m");
+        assert_eq!(product_of_instrument("SR501C5000"), "SR");
+        assert_eq!(product_of_instrument("SHFE.ag2412"), "ag");
+        assert_eq!(product_of_instrument("ag2412.SHFE"), "ag");
+        assert_eq!(product_of_instrument(" rb "), "rb");
+        assert_eq!(product_of_instrument("2412"), "");
+
+        let s_mgr = SessionManager::new_from_csv_content(include_str!("../tradesession.csv"))?;
+        assert_eq!(s_mgr.resolve_product("ag2412"), Some("ag"));
+        assert_eq!(s_mgr.resolve_product("AG2412"), Some("ag"));
+        assert_eq!(s_mgr.resolve_product("sr501"), Some("SR"));
+        assert_eq!(s_mgr.resolve_product("m2501-C-3000"), Some("m"));
+        assert_eq!(s_mgr.resolve_product("XX2501"), None);
+        assert_eq!(s_mgr.resolve_product("2501"), None);
+        assert!(s_mgr.get_session_for_instrument("IF2409").is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn product_exchange() -> anyhow::Result<()> {
+        let s_mgr = SessionManager::new_from_csv_content(include_str!("../tradesession.csv"))?;
+        assert_eq!(s_mgr.exchange_of("AP"), Some("CZCE"));
+        assert_eq!(s_mgr.exchange_of("XX"), None);
+        assert!(s_mgr.products_on("CZCE").contains(&"CF"));
+        assert!(!s_mgr.products_on("CZCE").contains(&"IF"));
+
+        let json =
+            r#"[{"Begin":"09:30:00","End":"11:30:00"},{"Begin":"13:00:00","End":"15:00:00"}]"#;
+        let quoted = json.replace('"', "\"\"");
+        let csv = format!(
+            "product,exchange,sessions\nT,AAA,\"{0}\"\nT,BBB,\"{0}\"\nU,BBB,\"{0}\"\n",
+            quoted
+        );
+        let mut s_mgr = SessionManager::new_from_csv_content(&csv)?;
+        assert_eq!(s_mgr.exchange_of("T"), Some("BBB"));
+        assert_eq!(s_mgr.products_on("BBB"), vec!["T", "U"]);
+        assert_eq!(s_mgr.products_on("AAA"), vec!["T"]);
+        assert!(s_mgr.get_session_on_exchange("AAA", "T").is_some());
+        assert!(s_mgr.get_session_on_exchange("AAA", "U").is_none());
+
+        // BBB的假日不影响AAA, 2025-01-02周四
+        s_mgr.reload_calendar_csv_content("exchange,date\nBBB,2025-01-02\n", false)?;
+        let dt = date_at_hms(&make_date(2025, 1, 2), 10, 0, 0);
+        assert_eq!(s_mgr.is_tradable("T", &dt), Some(false));
+        assert_eq!(s_mgr.is_tradable("U", &dt), Some(false));
+        s_mgr.add_record(ProductRecord::new(
+            "V",
+            "AAA",
+            s_mgr.record_on("AAA", "T").unwrap().history().clone(),
+        ));
+        assert_eq!(s_mgr.is_tradable("V", &dt), Some(true));
+
+        // add_session不改变所属交易所
+        s_mgr.add_session(
+            "V",
+            TradeSession::new_from_slices(&parse_json_slices(json)?),
+        );
+        assert_eq!(s_mgr.exchange_of("V"), Some("AAA"));
+        Ok(())
+    }
+
+    #[test]
+    fn trading_time_by_product() -> anyhow::Result<()> {
+        let s_mgr = SessionManager::new_from_csv_content(include_str!("../tradesession.csv"))?;
+        let fri = make_date(2025, 7, 25);
+        let start = date_at_hms(&fri, 11, 20, 0);
+        let end = s_mgr.add_trading_time("ag", &start, Duration::from_secs(30 * 60));
+        assert_eq!(end, Some(date_at_hms(&fri, 13, 50, 0)));
+        assert_eq!(
+            s_mgr.trading_duration_between("ag", &start, &end.unwrap()),
+            Some(Duration::from_secs(30 * 60))
+        );
+        assert_eq!(
+            s_mgr.add_trading_time("XX", &start, Duration::from_secs(60)),
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn time_zone_by_exchange() -> anyhow::Result<()> {
+        use crate::jcswitch::{local_to_zoned, parse_time_zone};
+
+        let csv_str = include_str!("../tradesession.csv");
+        let mut s_mgr = SessionManager::new_from_csv_content(csv_str)?;
+        let shanghai = parse_time_zone("Asia/Shanghai")?;
+        s_mgr.set_time_zone("SHFE", &shanghai);
+        assert!(s_mgr.get_session("ag").unwrap().time_zone().is_some());
+        assert!(s_mgr.get_session("AP").unwrap().time_zone().is_none());
+
+        // UTC 13:30是北京时间21:30, 属于下一个交易日
+        let utc = parse_time_zone("UTC")?;
+        let tue = make_date(2025, 7, 22);
+        let zoned = local_to_zoned(&date_at_hms(&tue, 13, 30, 0), &utc);
+        assert_eq!(s_mgr.is_tradable_zoned("ag", &zoned), Some(true));
+        assert_eq!(
+            s_mgr.trading_day_zoned("ag", &zoned),
+            Some(make_date(2025, 7, 23))
+        );
+        let zoned = local_to_zoned(&date_at_hms(&tue, 12, 0, 0), &utc);
+        assert_eq!(s_mgr.is_tradable_zoned("ag", &zoned), Some(false));
+        assert_eq!(s_mgr.is_tradable_zoned("XX", &zoned), None);
+
+        // 之后加载的品种也使用该交易所的时区
+        s_mgr.reload_csv_content(csv_str, true)?;
+        assert!(s_mgr.get_session("ag").unwrap().time_zone().is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn union_of_products() -> anyhow::Result<()> {
+        let mut s_mgr = SessionManager::new_from_csv_content(include_str!("../tradesession.csv"))?;
+        s_mgr.add_session("IF", TradeSession::new_stock_index_session());
+        // ag夜盘到2:30, CF夜盘到23:00, IF 9:30~11:30, 13:00~15:00
+        let union = s_mgr.union_of(&["ag", "CF", "IF"]).unwrap();
+        assert_eq!(*union.day_begin(), make_time(21, 0, 0));
+        assert!(union.in_session(&make_time(2, 0, 0), true, false));
+        assert!(union.in_session(&make_time(10, 20, 0), true, false));
+        assert!(union.in_session(&make_time(13, 10, 0), true, false));
+        assert!(!union.in_session(&make_time(12, 0, 0), true, false));
+
+        let both = s_mgr.intersection_of(&["ag", "CF"]).unwrap();
+        assert!(both.in_session(&make_time(22, 0, 0), true, false));
+        assert!(!both.in_session(&make_time(23, 30, 0), true, false));
+        assert!(both.is_subset_of(s_mgr.get_session("ag").unwrap()));
+
+        assert!(s_mgr.union_of(&["ag", "XX"]).is_none());
+        assert!(s_mgr.union_of(&[]).unwrap().get_slices().is_empty());
         Ok(())
     }
 }
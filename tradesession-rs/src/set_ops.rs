@@ -0,0 +1,198 @@
+use crate::tradesession::{SessionSlice, ShiftedTime, TradeSession};
+
+/// 时段的集合运算, 精确到秒, 结果按秒排序并合并相连的时段(参看post_fix),
+/// 结果使用self的anchor、时区及夜盘规则, other的时段按self的anchor重新平移,
+/// 结果不含集合竞价时段, 需要时可调用add_futures_auctions
+impl TradeSession {
+    /// 并集, 比如交易的几个品种任一开市时都要保持CTP连接
+    pub fn union(&self, other: &TradeSession) -> TradeSession {
+        let mut ranges = self.ranges();
+        ranges.extend(other.ranges_on(self.anchor_seconds()));
+        self.with_ranges(ranges)
+    }
+
+    /// 交集, 比如价差合约的两条腿同时开市的时段
+    pub fn intersection(&self, other: &TradeSession) -> TradeSession {
+        let lhs = self.ranges();
+        let rhs = other.ranges_on(self.anchor_seconds());
+        let mut ranges = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < lhs.len() && j < rhs.len() {
+            let begin = lhs[i].0.max(rhs[j].0);
+            let end = lhs[i].1.min(rhs[j].1);
+            if begin < end {
+                ranges.push((begin, end));
+            }
+            if lhs[i].1 < rhs[j].1 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        self.with_ranges(ranges)
+    }
+
+    /// 差集, self开市而other休市的时段
+    pub fn difference(&self, other: &TradeSession) -> TradeSession {
+        let rhs = other.ranges_on(self.anchor_seconds());
+        let mut ranges = vec![];
+        for (start, end) in self.ranges() {
+            let mut begin = start;
+            for &(b, e) in rhs.iter().filter(|(b, e)| *b < end && *e > start) {
+                if b > begin {
+                    ranges.push((begin, b));
+                }
+                begin = begin.max(e);
+            }
+            if begin < end {
+                ranges.push((begin, end));
+            }
+        }
+        self.with_ranges(ranges)
+    }
+
+    /// self的所有时段是否都在other之内, 空的session是任何session的子集
+    pub fn is_subset_of(&self, other: &TradeSession) -> bool {
+        self.difference(other).get_slices().is_empty()
+    }
+
+    /// 各时段平移之后的起止秒数, 已排序且不重叠
+    fn ranges(&self) -> Vec<(u32, u32)> {
+        self.get_slices()
+            .iter()
+            .map(|slice| (slice.begin().seconds(), slice.end().seconds()))
+            .collect()
+    }
+
+    /// 按anchor重新平移之后的各时段, 跨越anchor的时段拆分为两段, 排序并合并
+    fn ranges_on(&self, anchor: u32) -> Vec<(u32, u32)> {
+        let slices: Vec<SessionSlice> = self
+            .get_slices()
+            .iter()
+            .flat_map(|slice| slice.rebase(anchor))
+            .collect();
+        TradeSession::new_from_slices(&slices).ranges()
+    }
+
+    /// 用self的anchor、时区及夜盘规则, 以ranges为时段生成新的session
+    fn with_ranges(&self, ranges: Vec<(u32, u32)>) -> TradeSession {
+        let mut session = TradeSession::new_with_anchor(&self.anchor());
+        session.set_night_rule(self.night_rule());
+        if let Some(tz) = self.time_zone() {
+            session.set_time_zone(tz);
+        }
+        for (begin, end) in ranges {
+            let slice = SessionSlice::new_from_shifted_anchored(
+                ShiftedTime(begin),
+                ShiftedTime(end),
+                self.anchor_seconds(),
+            )
+            .expect("no fail");
+            session.add_slice_directly(slice);
+        }
+        session.post_fix();
+        session
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::jcswitch::*;
+
+    fn slice_times(session: &TradeSession) -> Vec<(MyTimeType, MyTimeType)> {
+        session
+            .get_slices()
+            .iter()
+            .map(|slice| {
+                let anchor = slice.anchor();
+                (
+                    slice.begin().nominal_time_anchored(anchor),
+                    slice.end().nominal_time_anchored(anchor),
+                )
+            })
+            .collect()
+    }
+
+    fn hm(list: &[(u32, u32, u32, u32)]) -> Vec<(MyTimeType, MyTimeType)> {
+        list.iter()
+            .map(|&(bh, bm, eh, em)| (make_time(bh, bm, 0), make_time(eh, em, 0)))
+            .collect()
+    }
+
+    #[test]
+    fn set_operations() {
+        let commodity = TradeSession::new_commodity_session_night();
+        let stock = TradeSession::new_stock_session();
+
+        let union = commodity.union(&stock);
+        assert_eq!(
+            slice_times(&union),
+            hm(&[(21, 0, 2, 30), (9, 0, 11, 30), (13, 0, 15, 0)])
+        );
+        assert!(union.auctions().is_empty());
+
+        let both = commodity.intersection(&stock);
+        assert_eq!(
+            slice_times(&both),
+            hm(&[(9, 30, 10, 15), (10, 30, 11, 30), (13, 30, 15, 0)])
+        );
+        assert!(!both.has_night());
+
+        let only = commodity.difference(&stock);
+        assert_eq!(slice_times(&only), hm(&[(21, 0, 2, 30), (9, 0, 9, 30)]));
+        assert_eq!(
+            slice_times(&stock.difference(&commodity)),
+            hm(&[(10, 15, 10, 30), (13, 0, 13, 30)])
+        );
+
+        assert!(both.is_subset_of(&commodity));
+        assert!(both.is_subset_of(&stock));
+        assert!(!commodity.is_subset_of(&stock));
+        assert!(commodity.is_subset_of(&TradeSession::new_full_session()));
+        assert!(TradeSession::new().is_subset_of(&stock));
+        assert!(
+            commodity
+                .intersection(&TradeSession::new())
+                .get_slices()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn set_operations_across_anchors() -> anyhow::Result<()> {
+        // anchor为0:00的全天时段, 按默认anchor(20:00)平移后拆分为两段, 再合并为一段
+        let mut all_day = TradeSession::new_with_anchor(&make_time(0, 0, 0));
+        all_day.add_slice(0, 0, 0, 0)?;
+        all_day.post_fix();
+        let commodity = TradeSession::new_commodity_session_night();
+        assert!(commodity.is_subset_of(&all_day));
+        let rest = TradeSession::new().union(&all_day).difference(&commodity);
+        assert_eq!(rest.anchor(), make_time(20, 0, 0));
+        assert_eq!(
+            slice_times(&rest),
+            hm(&[
+                (20, 0, 21, 0),
+                (2, 30, 9, 0),
+                (10, 15, 10, 30),
+                (11, 30, 13, 30),
+                (15, 0, 20, 0)
+            ])
+        );
+        // 结果使用self的anchor
+        let night = all_day.intersection(&commodity);
+        assert_eq!(night.anchor(), make_time(0, 0, 0));
+        assert_eq!(
+            slice_times(&night),
+            hm(&[
+                (0, 0, 2, 30),
+                (9, 0, 10, 15),
+                (10, 30, 11, 30),
+                (13, 30, 15, 0),
+                (21, 0, 0, 0)
+            ])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,257 @@
+use std::fmt::Display;
+
+use crate::jcswitch::*;
+use crate::session_mgr::SessionManager;
+use crate::tradesession::{ShiftedTime, TradeSession};
+
+/// 价差合约的一条腿在某个时段休市的原因, 时间均为实际时间
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GapReason {
+    /// 休市的腿已经收盘, 比如夜盘23:00结束, 参数为其收盘时间
+    Closed(MyTimeType),
+    /// 休市的腿尚未开盘, 比如股指期货9:30开盘, 参数为其开盘时间
+    NotOpen(MyTimeType),
+    /// 休市的腿在盘中休息, 比如10:15~10:30, 参数为休息的开始和结束时间
+    Break(MyTimeType, MyTimeType),
+    /// 休市的腿在这个夜盘或日盘没有交易, 比如没有夜盘
+    NoSession,
+}
+
+/// 只有一条腿开市的时段, 所有时间平移到以anchor为0点, 参看ShiftedTime
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LegGap {
+    begin: ShiftedTime,
+    end: ShiftedTime,
+    anchor: u32,
+    /// 开市的腿
+    open_leg: String,
+    /// 休市的腿
+    closed_leg: String,
+    reason: GapReason,
+}
+
+impl LegGap {
+    /// 注意：超前4小时
+    pub fn begin(&self) -> ShiftedTime {
+        self.begin
+    }
+    /// 注意：超前4小时
+    pub fn end(&self) -> ShiftedTime {
+        self.end
+    }
+    /// 开始时间, 实际时间
+    pub fn begin_time(&self) -> MyTimeType {
+        self.begin.nominal_time_anchored(self.anchor)
+    }
+    /// 结束时间, 实际时间
+    pub fn end_time(&self) -> MyTimeType {
+        self.end.nominal_time_anchored(self.anchor)
+    }
+    pub fn open_leg(&self) -> &str {
+        &self.open_leg
+    }
+    pub fn closed_leg(&self) -> &str {
+        &self.closed_leg
+    }
+    pub fn reason(&self) -> GapReason {
+        self.reason
+    }
+    /// 一个时间点在此时段内吗? 含开始，不含结束
+    pub fn contains(&self, ts: &MyTimeType) -> bool {
+        let sec = ShiftedTime::from_time_anchored(ts, self.anchor);
+        sec >= self.begin && sec < self.end
+    }
+}
+
+impl Display for LegGap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (open, closed) = (&self.open_leg, &self.closed_leg);
+        let (begin, end) = (self.begin_time(), self.end_time());
+        match self.reason {
+            GapReason::Closed(at) => {
+                write!(f, "{closed} closed at {at} while {open} runs to {end}")
+            }
+            GapReason::NotOpen(at) => {
+                write!(f, "{closed} opens at {at} while {open} runs from {begin}")
+            }
+            GapReason::Break(from, to) => write!(
+                f,
+                "{closed} on break {from}~{to} while {open} runs {begin}~{end}"
+            ),
+            GapReason::NoSession => {
+                write!(f, "{closed} has no session while {open} runs {begin}~{end}")
+            }
+        }
+    }
+}
+
+/// 价差合约两条腿的共同交易时段, 以及只有一条腿开市的时段及原因
+#[derive(Clone, Debug)]
+pub struct CommonSession {
+    /// 两条腿同时开市的时段, 使用leg_a的anchor, 不含集合竞价时段
+    session: TradeSession,
+    /// 只有一条腿开市的时段, 按时间排序
+    gaps: Vec<LegGap>,
+}
+
+impl CommonSession {
+    /// leg_a, leg_b为两条腿的名称(一般是品种代码), 用于LegGap
+    pub fn new(leg_a: &str, a: &TradeSession, leg_b: &str, b: &TradeSession) -> Self {
+        let session = a.intersection(b);
+        let empty = TradeSession::new_with_anchor(&a.anchor());
+        // 两条腿都按leg_a的anchor平移
+        let a = empty.union(a);
+        let b = empty.union(b);
+        let mut gaps = gaps_of(&a.difference(&b), leg_a, &b, leg_b);
+        gaps.extend(gaps_of(&b.difference(&a), leg_b, &a, leg_a));
+        gaps.sort_by_key(|gap| gap.begin);
+        Self { session, gaps }
+    }
+
+    /// 两条腿同时开市的时段
+    pub fn session(&self) -> &TradeSession {
+        &self.session
+    }
+
+    /// 只有一条腿开市的时段, 按时间排序
+    pub fn gaps(&self) -> &Vec<LegGap> {
+        &self.gaps
+    }
+
+    /// 一个时间点所在的只有一条腿开市的时段, 两条腿都开市或都休市时返回None
+    pub fn gap_at(&self, ts: &MyTimeType) -> Option<&LegGap> {
+        self.gaps.iter().find(|gap| gap.contains(ts))
+    }
+}
+
+/// only为只有open_leg开市的时段, closed为另一条腿的时段(同一anchor)
+fn gaps_of(
+    only: &TradeSession,
+    open_leg: &str,
+    closed: &TradeSession,
+    closed_leg: &str,
+) -> Vec<LegGap> {
+    let night_end = closed.night_end();
+    let anchor = closed.anchor_seconds();
+    let is_night = |sec: ShiftedTime| sec.seconds() < night_end;
+    let nominal = |sec: ShiftedTime| sec.nominal_time_anchored(anchor);
+    only.get_slices()
+        .iter()
+        .map(|gap| {
+            // 同为夜盘或同为日盘时, 休市腿在此时段前后的时段
+            let prev = closed
+                .get_slices()
+                .iter()
+                .filter(|slice| slice.end() <= gap.begin())
+                .filter(|slice| is_night(slice.begin()) == is_night(gap.begin()))
+                .map(|slice| slice.end())
+                .max();
+            let next = closed
+                .get_slices()
+                .iter()
+                .filter(|slice| slice.begin() >= gap.end())
+                .filter(|slice| is_night(slice.begin()) == is_night(gap.begin()))
+                .map(|slice| slice.begin())
+                .min();
+            let reason = match (prev, next) {
+                (Some(prev), Some(next)) => GapReason::Break(nominal(prev), nominal(next)),
+                (Some(prev), None) => GapReason::Closed(nominal(prev)),
+                (None, Some(next)) => GapReason::NotOpen(nominal(next)),
+                (None, None) => GapReason::NoSession,
+            };
+            LegGap {
+                begin: gap.begin(),
+                end: gap.end(),
+                anchor: gap.anchor(),
+                open_leg: open_leg.to_string(),
+                closed_leg: closed_leg.to_string(),
+                reason,
+            }
+        })
+        .collect()
+}
+
+impl SessionManager {
+    /// 价差合约两条腿的共同交易时段及只有一条腿开市的时段, 使用两个品种最新的版本,
+    /// 任一品种不存在时返回None
+    pub fn common_session(&self, leg_a: &str, leg_b: &str) -> Option<CommonSession> {
+        let a = self.get_session(leg_a)?;
+        let b = self.get_session(leg_b)?;
+        Some(CommonSession::new(leg_a, a, leg_b, b))
+    }
+
+    /// 一个日期时间点, 价差合约的两条腿都可以交易吗? 各自使用所属交易所的日历,
+    /// 任一品种不存在时返回None(即使另一条腿不可交易)
+    pub fn spread_tradable(&self, leg_a: &str, leg_b: &str, dt: &MyDateTimeType) -> Option<bool> {
+        let a = self.is_tradable(leg_a, dt)?;
+        let b = self.is_tradable(leg_b, dt)?;
+        Some(a && b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn common_session() -> anyhow::Result<()> {
+        let mut s_mgr = SessionManager::new_from_csv_content(include_str!("../tradesession.csv"))?;
+        s_mgr.add_session("IF", TradeSession::new_stock_index_session());
+
+        // ag夜盘到2:30, CF夜盘到23:00
+        let common = s_mgr.common_session("ag", "CF").unwrap();
+        assert!(
+            common
+                .session()
+                .in_session(&make_time(22, 0, 0), true, false)
+        );
+        assert!(
+            !common
+                .session()
+                .in_session(&make_time(23, 30, 0), true, false)
+        );
+        let gap = common.gap_at(&make_time(23, 30, 0)).unwrap();
+        assert_eq!(gap.closed_leg(), "CF");
+        assert_eq!(gap.reason(), GapReason::Closed(make_time(23, 0, 0)));
+        assert_eq!(gap.end_time(), make_time(2, 30, 0));
+        assert_eq!(
+            gap.to_string(),
+            "CF closed at 23:00:00 while ag runs to 02:30:00"
+        );
+        assert_eq!(common.gaps().len(), 1);
+        assert!(common.gap_at(&make_time(10, 0, 0)).is_none());
+
+        // 股指期货没有夜盘, 9:30开盘, 午休到13:00
+        let common = s_mgr.common_session("ag", "IF").unwrap();
+        let reasons: Vec<(&str, GapReason)> = common
+            .gaps()
+            .iter()
+            .map(|gap| (gap.closed_leg(), gap.reason()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                ("IF", GapReason::NoSession),
+                ("IF", GapReason::NotOpen(make_time(9, 30, 0))),
+                (
+                    "ag",
+                    GapReason::Break(make_time(10, 15, 0), make_time(10, 30, 0))
+                ),
+                (
+                    "ag",
+                    GapReason::Break(make_time(11, 30, 0), make_time(13, 30, 0))
+                ),
+            ]
+        );
+        assert!(s_mgr.common_session("ag", "XX").is_none());
+
+        // 2025-07-25周五23:30, CF夜盘已收盘
+        let dt = date_at_hms(&make_date(2025, 7, 25), 23, 30, 0);
+        assert_eq!(s_mgr.spread_tradable("ag", "CF", &dt), Some(false));
+        assert_eq!(s_mgr.spread_tradable("CF", "XX", &dt), None);
+        let dt = date_at_hms(&make_date(2025, 7, 25), 22, 30, 0);
+        assert_eq!(s_mgr.spread_tradable("ag", "CF", &dt), Some(true));
+        Ok(())
+    }
+}
@@ -1,11 +1,12 @@
-use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::{collections::BTreeSet, fmt::Display};
-
-#[cfg(feature = "with-chrono")]
-use chrono::Timelike;
+use std::{collections::BTreeSet, fmt::Display, time::Duration};
 
+use crate::bitmap::SecondBitmap;
+use crate::calendar::TradingCalendar;
+use crate::error::{Result, TradeSessionError};
 use crate::jcswitch::*;
+use crate::phase::{AuctionWindow, TradingPhase};
 
 /// 4小时对应的总秒数
 pub const SECS_IN_FOUR_HOURS: u32 = 4 * 60 * 60;
@@ -13,10 +14,42 @@ pub const SECS_IN_FOUR_HOURS: u32 = 4 * 60 * 60;
 /// 每天的总秒数
 pub const SECS_IN_ONE_DAY: u32 = 86400;
 
-/// 将日内时间增加4小时后得到的时间，用于规避夜盘跨零点的问题
-/// 即夜里20:00:00作为新交易日的0:00:00
+/// 默认的交易日开始时间(anchor), 自零点起的秒数,
+/// 国内期货以夜里20:00作为新交易日的开始, 即所有时间增加4小时
+pub const DEFAULT_ANCHOR: u32 = 20 * 3600;
+
+/// 每秒的纳秒数
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+
+/// next_open、prev_close等逐日查找的最大天数(约10年), 日历全部休市时不至于无限循环
+pub(crate) const MAX_SCAN_DAYS: u32 = 3660;
+
+/// 平移后在此之前的都是夜盘时间(含跨零点部分), 即6:00之前, 默认anchor时为(6+4)*3600 = 36000,
+/// anchor为零点时没有夜盘
+pub(crate) fn night_part_end(anchor: u32) -> u32 {
+    let to_midnight = (SECS_IN_ONE_DAY - anchor) % SECS_IN_ONE_DAY;
+    if to_midnight == 0 {
+        0
+    } else {
+        to_midnight + 6 * 3600
+    }
+}
+
+/// 夜盘(交易日开始之后到早盘之前的时段)在哪些晚上开市
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NightRule {
+    /// 国内期货: 交易日晚上开市, 属于下一个交易日, 比如周五晚上的夜盘属于下周一,
+    /// 节假日前最后一个交易日晚上休市, 参看TradingCalendar::has_night_session
+    #[default]
+    AfterTradingDay,
+    /// CME等: 交易日前一个自然日晚上开市, 比如周日晚上开市属于周一, 周五晚上休市
+    BeforeTradingDay,
+}
+
+/// 将日内时间平移到以交易日开始时间(anchor)为0点后得到的时间，用于规避夜盘跨零点的问题
+/// 默认anchor为20:00, 即增加4小时, 夜里20:00:00作为新交易日的0:00:00
 /// 但不超过24:00:00，对其模86400
-/// 以秒作为字段进行记录和比较
+/// 以秒作为字段进行记录和比较, 不带anchor的方法均使用DEFAULT_ANCHOR
 #[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
 pub struct ShiftedTime(pub u32);
 
@@ -28,8 +61,25 @@ impl ShiftedTime {
     }
     /// 原始秒数,尚未增加4小时
     pub fn new_from_midnight_seconds(seconds: u32) -> Self {
-        let secs = (seconds + SECS_IN_FOUR_HOURS) % SECS_IN_ONE_DAY;
-        Self(secs)
+        Self::new_from_midnight_seconds_anchored(seconds, DEFAULT_ANCHOR)
+    }
+    /// 原始秒数,尚未平移, anchor为交易日开始时间(自零点起的秒数)
+    pub fn new_from_midnight_seconds_anchored(seconds: u32, anchor: u32) -> Self {
+        Self((seconds + SECS_IN_ONE_DAY - anchor) % SECS_IN_ONE_DAY)
+    }
+    /// 原始时间, 纳秒数非零时归于后一秒, anchor为交易日开始时间(自零点起的秒数)
+    pub fn from_time_anchored(t: &MyTimeType, anchor: u32) -> Self {
+        // jiff下nanosecond()仅返回不足1微秒的部分, 所以统一用time_to_midnight_nanos计算
+        Self::from_nanos_anchored(time_to_midnight_nanos(t), anchor)
+    }
+    /// 自零点起的纳秒数或epoch纳秒数(按一天取余), 纳秒数非零时归于后一秒
+    pub fn from_nanos_anchored(nanos: i64, anchor: u32) -> Self {
+        let nanos = nanos.rem_euclid(SECS_IN_ONE_DAY as i64 * NANOS_PER_SEC);
+        let mut sec = (nanos / NANOS_PER_SEC) as u32;
+        if nanos % NANOS_PER_SEC > 0 {
+            sec += 1;
+        }
+        Self::new_from_midnight_seconds_anchored(sec % SECS_IN_ONE_DAY, anchor)
     }
     /// seconds已经增加4小时
     pub fn new_from_shifted(seconds: u32) -> Self {
@@ -43,12 +93,20 @@ impl ShiftedTime {
 
     /// 名义时间对应的秒数
     pub fn nominal_seconds(&self) -> u32 {
-        (self.0 + SECS_IN_ONE_DAY - SECS_IN_FOUR_HOURS) % SECS_IN_ONE_DAY
+        self.nominal_seconds_anchored(DEFAULT_ANCHOR)
+    }
+    /// 名义时间对应的秒数, anchor为交易日开始时间(自零点起的秒数)
+    pub fn nominal_seconds_anchored(&self, anchor: u32) -> u32 {
+        (self.0 + anchor) % SECS_IN_ONE_DAY
     }
 
     /// 名义时间
     pub fn nominal_time(&self) -> MyTimeType {
-        let secs = self.nominal_seconds();
+        self.nominal_time_anchored(DEFAULT_ANCHOR)
+    }
+    /// 名义时间, anchor为交易日开始时间(自零点起的秒数)
+    pub fn nominal_time_anchored(&self, anchor: u32) -> MyTimeType {
+        let secs = self.nominal_seconds_anchored(anchor);
         let h = secs / (60 * 60);
         let m = secs % (60 * 60) / 60;
         let s = secs % 60;
@@ -56,7 +114,8 @@ impl ShiftedTime {
     }
 
     pub fn shifted_time(&self) -> MyTimeType {
-        let secs = self.0;
+        // 结束于anchor的时段, 其结束时间为86400
+        let secs = self.0 % SECS_IN_ONE_DAY;
         let h = secs / (60 * 60);
         let m = secs % (60 * 60) / 60;
         let s = secs % 60;
@@ -106,15 +165,7 @@ impl From<&MyTimeType> for ShiftedTime {
     // 如果不含集合竞价，它是[9:00:00～9:01:00], 第二个(9:01:00~9:02:00]
     // 如果包含集合竞价，它是[8:59:00～9:01:00], 第二个(9:01:00~9:02:00]
     fn from(t: &MyTimeType) -> Self {
-        let mut sec = t.hour() as u32 * 3600
-            + t.minute() as u32 * 60
-            + t.second() as u32
-            + SECS_IN_FOUR_HOURS;
-        if t.nanosecond() > 0 {
-            sec += 1;
-        }
-        let sec = sec % SECS_IN_ONE_DAY;
-        Self(sec)
+        Self::from_time_anchored(t, DEFAULT_ANCHOR)
     }
 }
 
@@ -124,46 +175,68 @@ impl From<MyTimeType> for ShiftedTime {
     }
 }
 
-impl Into<MyTimeType> for ShiftedTime {
-    fn into(self) -> MyTimeType {
-        self.nominal_time()
+impl From<ShiftedTime> for MyTimeType {
+    fn from(t: ShiftedTime) -> Self {
+        t.nominal_time()
     }
 }
 
-impl Into<MyTimeType> for &ShiftedTime {
-    fn into(self) -> MyTimeType {
-        self.nominal_time()
+impl From<&ShiftedTime> for MyTimeType {
+    fn from(t: &ShiftedTime) -> Self {
+        t.nominal_time()
     }
 }
 
 /// 内部保存开始和结束时间的秒数,
-/// 由于需要处理夜盘跨零点的问题, 所有时间比实际时间增加4小时,
-/// 即夜里20:00:00作为新交易日的0:00:00
+/// 由于需要处理夜盘跨零点的问题, 所有时间平移到以交易日开始时间(anchor)为0点,
+/// 默认anchor为20:00, 即比实际时间增加4小时, 夜里20:00:00作为新交易日的0:00:00
 /// 但不超过24:00:00，对其模86400
 #[derive(Clone, Debug, Copy)]
 pub struct SessionSlice {
     begin: ShiftedTime,
     end: ShiftedTime,
+    /// 交易日开始时间, 自零点起的秒数
+    anchor: u32,
 }
 
 impl SessionSlice {
-    /// 用Time构造, begin必须小于end(除非开始时间大于20点且结束时间跨零点)
+    /// 用Time构造, begin必须小于end(除非开始时间大于20点且结束时间跨零点), 结束于20:00时视为交易日结束
     pub fn new(begin: &MyTimeType, end: &MyTimeType) -> Result<Self> {
-        Self::new_from_shifted(ShiftedTime::from(begin), ShiftedTime::from(end))
+        Self::new_anchored(begin, end, DEFAULT_ANCHOR)
+    }
+
+    /// 用Time构造, anchor为交易日开始时间(自零点起的秒数),
+    /// begin必须小于end(除非开始时间不早于anchor且结束时间跨零点),
+    /// 结束时间等于anchor时视为交易日结束, 同TradeSession::add_slice
+    pub fn new_anchored(begin: &MyTimeType, end: &MyTimeType, anchor: u32) -> Result<Self> {
+        let mut end = ShiftedTime::from_time_anchored(end, anchor);
+        if end.seconds() == 0 {
+            end = ShiftedTime(SECS_IN_ONE_DAY);
+        }
+        Self::new_from_shifted_anchored(ShiftedTime::from_time_anchored(begin, anchor), end, anchor)
     }
 
     /// 注意： 输入数据必须已经加过4小时了, begin必须小于end
     pub fn new_from_shifted(begin_sec: ShiftedTime, end_sec: ShiftedTime) -> Result<Self> {
+        Self::new_from_shifted_anchored(begin_sec, end_sec, DEFAULT_ANCHOR)
+    }
+
+    /// 注意： 输入数据必须已经平移到以anchor为0点, begin必须小于end
+    pub fn new_from_shifted_anchored(
+        begin_sec: ShiftedTime,
+        end_sec: ShiftedTime,
+        anchor: u32,
+    ) -> Result<Self> {
         if begin_sec >= end_sec {
-            return Err(anyhow!(
+            return Err(TradeSessionError::InvalidSlice(format!(
                 "SessionSlice: begin must less than end, but got begin: {}, end: {}",
-                begin_sec,
-                end_sec
-            ));
+                begin_sec, end_sec
+            )));
         }
         Ok(Self {
             begin: begin_sec,
             end: end_sec,
+            anchor: anchor % SECS_IN_ONE_DAY,
         })
     }
 
@@ -187,10 +260,38 @@ impl SessionSlice {
     pub fn end(&self) -> ShiftedTime {
         self.end
     }
+    /// 交易日开始时间, 自零点起的秒数
+    pub fn anchor(&self) -> u32 {
+        self.anchor
+    }
+
+    /// 平移到以另一个anchor为0点, 跨越新anchor的时段会被拆分为两段
+    pub fn rebase(&self, anchor: u32) -> Vec<SessionSlice> {
+        let anchor = anchor % SECS_IN_ONE_DAY;
+        if anchor == self.anchor {
+            return vec![*self];
+        }
+        let nominal = self.begin.nominal_seconds_anchored(self.anchor);
+        let begin = ShiftedTime::new_from_midnight_seconds_anchored(nominal, anchor).seconds();
+        let end = begin + (self.end.seconds() - self.begin.seconds());
+        let slice = |begin, end| SessionSlice {
+            begin: ShiftedTime(begin),
+            end: ShiftedTime(end),
+            anchor,
+        };
+        if end <= SECS_IN_ONE_DAY {
+            vec![slice(begin, end)]
+        } else {
+            vec![
+                slice(0, end - SECS_IN_ONE_DAY),
+                slice(begin, SECS_IN_ONE_DAY),
+            ]
+        }
+    }
 
     /// 一个时间点, 在时段内吗? 一般应含开始(include_begin?), 是否含结束(include_end?)
     pub fn in_slice(&self, ts: &MyTimeType, include_begin: bool, include_end: bool) -> bool {
-        let sec = ShiftedTime::from(ts);
+        let sec = ShiftedTime::from_time_anchored(ts, self.anchor);
         match (include_begin, include_end) {
             (true, true) => sec >= self.begin && sec <= self.end,
             (true, false) => sec >= self.begin && sec < self.end,
@@ -199,17 +300,18 @@ impl SessionSlice {
         }
     }
 
-    /// 是否为夜盘交易， 所有夜盘的开始时间都是21:00
+    /// 是否为夜盘交易, 即在交易日开始(anchor)之后、零点之前开始的时段,
+    /// 国内期货所有夜盘的开始时间都是21:00
     pub fn is_night(&self) -> bool {
-        // 21:00前移4小时为1:00, 数值应是3600秒
-        self.begin.seconds() == 3600
+        self.begin.seconds() < (SECS_IN_ONE_DAY - self.anchor) % SECS_IN_ONE_DAY
     }
 
     /// 获取此时间片对应分钟(最大不超过1440,u16足够)的数组，
     /// 含开始，不含结束， 注意：所有数值超前4小时
+    /// 开始或结束不是整分钟时, 包含时段所覆盖的不完整的分钟, 比如11:29:30结束时包含11:29
     pub fn minutes_list(&self) -> BTreeSet<u16> {
         let start_minute = (self.begin.seconds() / 60) as u16;
-        let end_minute = (self.end.seconds() / 60) as u16;
+        let end_minute = self.end.seconds().div_ceil(60) as u16;
         // 注意：end_minute不包含在内
         (start_minute..end_minute).collect()
     }
@@ -224,8 +326,8 @@ impl Display for SessionSlice {
                 "raw({}~{}), act({}~{})",
                 self.begin.shifted_time().format("%R"),
                 self.end.shifted_time().format("%R"),
-                self.begin.nominal_time().format("%R"),
-                self.end.nominal_time().format("%R"),
+                self.begin.nominal_time_anchored(self.anchor).format("%R"),
+                self.end.nominal_time_anchored(self.anchor).format("%R"),
             )
         }
         #[cfg(feature = "with-jiff")]
@@ -235,8 +337,12 @@ impl Display for SessionSlice {
                 "raw({}~{}), act({}~{})",
                 self.begin.shifted_time().strftime("%H:%M"),
                 self.end.shifted_time().strftime("%H:%M"),
-                self.begin.nominal_time().strftime("%H:%M"),
-                self.end.nominal_time().strftime("%H:%M"),
+                self.begin
+                    .nominal_time_anchored(self.anchor)
+                    .strftime("%H:%M"),
+                self.end
+                    .nominal_time_anchored(self.anchor)
+                    .strftime("%H:%M"),
             )
         }
     }
@@ -251,22 +357,64 @@ pub struct TradeSession {
     day_end: MyTimeType,
     /// 该品种早盘开始时间，9:00/9:15/9:30,非夜盘品种跟day_begin相同
     morning_begin: MyTimeType,
+    /// 集合竞价时段, 按开始时间排序
+    auctions: Vec<AuctionWindow>,
+    /// 交易日开始时间, 自零点起的秒数, 国内默认20:00
+    anchor: u32,
+    /// 时段所用的时区, None表示不关心时区, 参看zoned.rs中带时区的查询
+    time_zone: Option<MyTimeZone>,
+    /// 夜盘在哪些晚上开市
+    night_rule: NightRule,
+    /// 每一秒是否在时段内, post_fix时生成, 供in_session使用
+    bitmap: SecondBitmap,
 }
 
 impl TradeSession {
     pub fn new() -> Self {
         let day_begin = make_time(9, 0, 0);
         let day_end = make_time(15, 0, 0);
-        let morning_begin = day_begin.clone();
+        let morning_begin = day_begin;
         Self {
             slices: vec![],
             day_begin,
             day_end,
             morning_begin,
+            auctions: vec![],
+            anchor: DEFAULT_ANCHOR,
+            time_zone: None,
+            night_rule: NightRule::default(),
+            bitmap: SecondBitmap::default(),
+        }
+    }
+    /// 以anchor作为交易日开始时间, 比如CME Globex的17:00, 加密货币的0:00,
+    /// 之后add_slice添加的时段都按此anchor平移
+    pub fn new_with_anchor(anchor: &MyTimeType) -> Self {
+        let mut session = Self::new();
+        session.anchor = (time_to_midnight_nanos(anchor) / NANOS_PER_SEC) as u32;
+        session
+    }
+    /// 同new_from_slices, 但时段重叠(首尾相连不算)时返回OverlappingSlice错误, 而不是合并
+    pub fn try_new_from_slices(slices: &Vec<SessionSlice>) -> Result<Self> {
+        let anchor = slices.first().map_or(DEFAULT_ANCHOR, |slice| slice.anchor);
+        let mut rebased: Vec<SessionSlice> = slices
+            .iter()
+            .flat_map(|slice| slice.rebase(anchor))
+            .collect();
+        rebased.sort_by_key(|slice| slice.begin);
+        if let Some(pair) = rebased.windows(2).find(|pair| pair[1].begin < pair[0].end) {
+            return Err(TradeSessionError::OverlappingSlice(format!(
+                "SessionSlice: {} overlaps {}",
+                pair[0], pair[1]
+            )));
         }
+        Ok(Self::new_from_slices(slices))
     }
+    /// anchor取第一个slice的anchor, 其他slice按此anchor重新平移
     pub fn new_from_slices(slices: &Vec<SessionSlice>) -> Self {
         let mut session = Self::new();
+        if let Some(first) = slices.first() {
+            session.anchor = first.anchor;
+        }
         session.slices.extend(slices);
         session.post_fix();
         session
@@ -288,12 +436,24 @@ impl TradeSession {
         ss.add_slice(9, 30, 11, 30).expect("no fail");
         ss.add_slice(13, 0, 15, 0).expect("no fail");
         ss.post_fix();
+        // 开盘集合竞价09:15~09:25, 09:25撮合之后到09:30接受报单并排队, 收盘集合竞价14:57~15:00
+        let open = AuctionWindow::new_open_queued(
+            &make_time(9, 15, 0),
+            &make_time(9, 25, 0),
+            &make_time(9, 30, 0),
+        );
+        let close = AuctionWindow::new_close(&make_time(14, 57, 0), &make_time(15, 0, 0));
+        ss.add_auction(open.expect("no fail")).expect("no fail");
+        ss.add_auction(close.expect("no fail")).expect("no fail");
         ss
     }
 
-    /// 生成一个股指期货的交易时段, 现在股指期货跟股票一样
+    /// 生成一个股指期货的交易时段, 现在股指期货跟股票一样, 但集合竞价按期货规则
     pub fn new_stock_index_session() -> Self {
-        Self::new_stock_session()
+        let mut ss = Self::new_stock_session();
+        ss.auctions.clear();
+        ss.add_futures_auctions();
+        ss
     }
 
     /// 生成一个国债期货的交易时段, 比金融期货多15分钟
@@ -302,6 +462,7 @@ impl TradeSession {
         ss.add_slice(9, 30, 11, 30).expect("no fail");
         ss.add_slice(13, 0, 15, 15).expect("no fail");
         ss.post_fix();
+        ss.add_futures_auctions();
         ss
     }
 
@@ -312,6 +473,7 @@ impl TradeSession {
         ss.add_slice(10, 30, 11, 30).expect("no fail");
         ss.add_slice(13, 30, 15, 0).expect("no fail");
         ss.post_fix();
+        ss.add_futures_auctions();
         ss
     }
 
@@ -324,6 +486,7 @@ impl TradeSession {
         ss.add_slice(10, 30, 11, 30).expect("no fail");
         ss.add_slice(13, 30, 15, 0).expect("no fail");
         ss.post_fix();
+        ss.add_futures_auctions();
         ss
     }
 
@@ -334,6 +497,7 @@ impl TradeSession {
         ss.add_slice(9, 0, 11, 30).expect("no fail");
         ss.add_slice(13, 0, 15, 15).expect("no fail");
         ss.post_fix();
+        ss.add_futures_auctions();
         ss
     }
 
@@ -341,8 +505,9 @@ impl TradeSession {
     pub fn get_slices(&self) -> &Vec<SessionSlice> {
         &self.slices
     }
-    /// 注意： 所有数值比实际时间多4小时
+    /// 注意： 所有数值比实际时间多4小时, 修改之后应该调用post_fix
     pub fn get_slices_mut(&mut self) -> &mut Vec<SessionSlice> {
+        self.bitmap.invalidate();
         &mut self.slices
     }
     /// 该品种日线开始时间，9:00/9:15/9:30/21:00, 一般是集合竞价所在的时间
@@ -357,14 +522,158 @@ impl TradeSession {
     pub fn morning_begin(&self) -> &MyTimeType {
         &self.morning_begin
     }
+    /// 交易日开始时间, 国内默认20:00
+    pub fn anchor(&self) -> MyTimeType {
+        ShiftedTime(0).nominal_time_anchored(self.anchor)
+    }
+    /// 时段所用的时区, 没有设置时返回None
+    pub fn time_zone(&self) -> Option<&MyTimeZone> {
+        self.time_zone.as_ref()
+    }
+    /// 设置时段所用的时区, 比如CME为America/Chicago, 时段仍按当地时间添加, 夏令时切换时当地时间不变
+    pub fn set_time_zone(&mut self, tz: &MyTimeZone) -> &mut Self {
+        self.time_zone = Some(tz.to_owned());
+        self
+    }
+    /// 夜盘在哪些晚上开市
+    pub fn night_rule(&self) -> NightRule {
+        self.night_rule
+    }
+    /// 设置夜盘在哪些晚上开市, 比如CME为NightRule::BeforeTradingDay
+    pub fn set_night_rule(&mut self, rule: NightRule) -> &mut Self {
+        self.night_rule = rule;
+        self
+    }
+    /// 交易日开始时间, 自零点起的秒数
+    pub(crate) fn anchor_seconds(&self) -> u32 {
+        self.anchor
+    }
+    /// 按本session的anchor平移, 纳秒数非零时归于后一秒
+    pub(crate) fn shift(&self, ts: &MyTimeType) -> ShiftedTime {
+        ShiftedTime::from_time_anchored(ts, self.anchor)
+    }
+    /// 平移后在此之前的都是夜盘时间(含跨零点部分)
+    pub(crate) fn night_end(&self) -> u32 {
+        night_part_end(self.anchor)
+    }
     /// 是否有夜盘交易
     pub fn has_night(&self) -> bool {
         self.slices.iter().any(|slice| slice.is_night())
     }
+    /// 集合竞价时段, 按开始时间排序
+    pub fn auctions(&self) -> &Vec<AuctionWindow> {
+        &self.auctions
+    }
+    /// 添加集合竞价时段, 与已有时段重叠的旧时段会被替换,
+    /// 按本session的anchor重新平移, 跨越anchor时返回错误
+    pub fn add_auction(&mut self, auction: AuctionWindow) -> Result<&mut Self> {
+        let auction = auction.rebase(self.anchor)?;
+        self.auctions
+            .retain(|a| a.end() <= auction.begin() || a.begin() >= auction.end());
+        self.auctions.push(auction);
+        self.auctions.sort_by_key(|a| a.begin());
+        Ok(self)
+    }
+    /// 按期货规则添加开盘集合竞价: 交易日第一个时段开始前5分钟报单, 最后1分钟撮合,
+    /// 比如08:55~08:59报单, 08:59~09:00撮合, 有夜盘的品种仅夜盘开盘前20:55~21:00有集合竞价
+    pub fn add_futures_auctions(&mut self) -> &mut Self {
+        let Some(begin) = self.slices.first().map(|slice| slice.begin) else {
+            return self;
+        };
+        if begin.seconds() < 300 {
+            return self;
+        }
+        let auction = AuctionWindow::new_from_shifted_anchored(
+            false,
+            ShiftedTime(begin.seconds() - 300),
+            ShiftedTime(begin.seconds() - 60),
+            begin,
+            self.anchor,
+        )
+        .expect("no fail");
+        self.add_auction(auction).expect("no fail")
+    }
+
+    /// 一个时间点所处的交易阶段, 集合竞价优先于连续交易,
+    /// 两个时段之间为盘中休息, 但夜盘收盘到早盘开盘之间, 以及开盘集合竞价之前为休市
+    pub fn phase_at(&self, ts: &MyTimeType) -> TradingPhase {
+        let sec = self.shift(ts);
+        let night_end = self.night_end();
+        if let Some(phase) = self.auctions.iter().find_map(|a| a.phase_at(sec)) {
+            return phase;
+        }
+        if self.in_session(ts, true, false) {
+            return TradingPhase::Continuous;
+        }
+        let is_break = self.slices.windows(2).any(|pair| {
+            let (prev, next) = (&pair[0], &pair[1]);
+            prev.end <= sec
+                && sec < next.begin
+                && (prev.begin.seconds() < night_end) == (next.begin.seconds() < night_end)
+                && !self
+                    .auctions
+                    .iter()
+                    .any(|a| !a.is_closing() && a.end() == next.begin)
+        });
+        if is_break {
+            TradingPhase::Break
+        } else {
+            TradingPhase::Closed
+        }
+    }
+
+    /// 一个日期时间点所处的交易阶段, 休市的日期(含节假日前没有的夜盘)为Closed,
+    /// calendar为None时仅排除周末
+    pub fn phase_at_datetime(
+        &self,
+        dt: &MyDateTimeType,
+        calendar: Option<&TradingCalendar>,
+    ) -> TradingPhase {
+        let phase = self.phase_at(&dt.time());
+        if phase == TradingPhase::Closed {
+            return phase;
+        }
+        if self.is_open_date(dt, calendar) {
+            phase
+        } else {
+            TradingPhase::Closed
+        }
+    }
 
     /// 一个时间点, 在时段内吗? 一般应含开始(include_begin?), 是否含结束(include_end?)
+    /// post_fix之后查表, O(1), 否则逐个时段比较, 参看in_session_scan
     pub fn in_session(&self, ts: &MyTimeType, include_begin: bool, include_end: bool) -> bool {
-        let sec = ShiftedTime::from(ts);
+        self.contains(self.shift(ts), include_begin, include_end)
+    }
+
+    /// 批量判断时间点在时段内吗? ts为自零点起的纳秒数, 或不带时区的日期时间的epoch纳秒数
+    /// (比如NumPy的datetime64[ns]), 均按一天取余, 结果与逐个调用in_session相同
+    pub fn in_session_batch(
+        &self,
+        ts: &[i64],
+        include_begin: bool,
+        include_end: bool,
+    ) -> Vec<bool> {
+        ts.iter()
+            .map(|&nanos| {
+                let sec = ShiftedTime::from_nanos_anchored(nanos, self.anchor);
+                self.contains(sec, include_begin, include_end)
+            })
+            .collect()
+    }
+
+    /// 同in_session, 但总是逐个时段比较, 供对照及基准测试
+    pub fn in_session_scan(&self, ts: &MyTimeType, include_begin: bool, include_end: bool) -> bool {
+        self.scan(self.shift(ts), include_begin, include_end)
+    }
+
+    fn contains(&self, sec: ShiftedTime, include_begin: bool, include_end: bool) -> bool {
+        self.bitmap
+            .contains(sec.seconds(), include_begin, include_end)
+            .unwrap_or_else(|| self.scan(sec, include_begin, include_end))
+    }
+
+    fn scan(&self, sec: ShiftedTime, include_begin: bool, include_end: bool) -> bool {
         for slice in &self.slices {
             // 由于每一次调用slice.in_slice(&ts,...)内部都需要转换ts到sec,
             // 所以这里复制代码逻辑，仅转换ts到sec一次
@@ -379,7 +688,7 @@ impl TradeSession {
                 return true;
             }
         }
-        return false;
+        false
     }
 
     /// start, end之间任意时间点落在session中吗?
@@ -389,8 +698,8 @@ impl TradeSession {
         end: &MyTimeType,
         include_begin_end: bool,
     ) -> bool {
-        let start = ShiftedTime::from(start);
-        let end = ShiftedTime::from(end);
+        let start = self.shift(start);
+        let end = self.shift(end);
         self.slices.iter().any(|slice| {
             if include_begin_end {
                 start <= slice.end && end >= slice.begin
@@ -400,13 +709,310 @@ impl TradeSession {
         })
     }
 
+    /// 一个日期时间点, 按交易日历可以交易吗? 即in_session_at(dt, true, false, calendar)
+    pub fn is_tradable(&self, dt: &MyDateTimeType, calendar: Option<&TradingCalendar>) -> bool {
+        self.in_session_at(dt, true, false, calendar)
+    }
+
+    /// 一个日期时间点, 在时段内吗? 除了时间须在时段内, 日期还须是交易日,
+    /// 夜盘(含跨零点部分)要求夜盘开始的那天晚上确实有夜盘, 参看NightRule, 比如节假日前最后一个交易日晚上没有夜盘,
+    /// calendar为None时仅排除周末
+    pub fn in_session_at(
+        &self,
+        dt: &MyDateTimeType,
+        include_begin: bool,
+        include_end: bool,
+        calendar: Option<&TradingCalendar>,
+    ) -> bool {
+        if !self.in_session(&dt.time(), include_begin, include_end) {
+            return false;
+        }
+        self.is_open_date(dt, calendar)
+    }
+
+    /// start, end之间任意日期时间点落在session中吗? 按交易日历排除休市的时段,
+    /// calendar为None时仅排除周末
+    pub fn any_in_session_between(
+        &self,
+        start: &MyDateTimeType,
+        end: &MyDateTimeType,
+        include_begin_end: bool,
+        calendar: Option<&TradingCalendar>,
+    ) -> bool {
+        if start > end {
+            return false;
+        }
+        let default = TradingCalendar::new();
+        let calendar = calendar.unwrap_or(&default);
+        let last = self.shifted_date(end);
+        let mut date = self.shifted_date(start);
+        while date <= last {
+            let found = self
+                .datetime_slices(&date, calendar)
+                .iter()
+                .any(|(begin, finish)| {
+                    if include_begin_end {
+                        start <= finish && end >= begin
+                    } else {
+                        start < finish && end > begin
+                    }
+                });
+            if found {
+                return true;
+            }
+            date = tomorrow(&date);
+        }
+        false
+    }
+
+    /// dt所在时段的实际起止时间, 含开始，不含结束, 不在时段内时返回None
+    pub fn current_slice(
+        &self,
+        dt: &MyDateTimeType,
+        calendar: Option<&TradingCalendar>,
+    ) -> Option<(MyDateTimeType, MyDateTimeType)> {
+        let default = TradingCalendar::new();
+        let calendar = calendar.unwrap_or(&default);
+        self.datetime_slices(&self.shifted_date(dt), calendar)
+            .into_iter()
+            .find(|(begin, end)| begin <= dt && dt < end)
+    }
+
+    /// dt之后(不含dt)的下一个时段开始时间, 跨越夜盘零点、周末及节假日,
+    /// calendar为None时仅跳过周末, 没有任何时段或MAX_SCAN_DAYS天内都休市时返回None
+    pub fn next_open(
+        &self,
+        dt: &MyDateTimeType,
+        calendar: Option<&TradingCalendar>,
+    ) -> Option<MyDateTimeType> {
+        self.scan_forward(dt, calendar, |(begin, _)| begin)
+    }
+
+    /// dt之后(不含dt)的下一个时段结束时间, 在时段内时即为当前时段的结束时间,
+    /// calendar为None时仅跳过周末, 没有任何时段或MAX_SCAN_DAYS天内都休市时返回None
+    pub fn next_close(
+        &self,
+        dt: &MyDateTimeType,
+        calendar: Option<&TradingCalendar>,
+    ) -> Option<MyDateTimeType> {
+        self.scan_forward(dt, calendar, |(_, end)| end)
+    }
+
+    /// dt之前(含dt)的最近一个时段结束时间,
+    /// calendar为None时仅跳过周末, 没有任何时段或MAX_SCAN_DAYS天内都休市时返回None
+    pub fn prev_close(
+        &self,
+        dt: &MyDateTimeType,
+        calendar: Option<&TradingCalendar>,
+    ) -> Option<MyDateTimeType> {
+        if self.slices.is_empty() {
+            return None;
+        }
+        let default = TradingCalendar::new();
+        let calendar = calendar.unwrap_or(&default);
+        let mut date = self.shifted_date(dt);
+        for _ in 0..MAX_SCAN_DAYS {
+            let found = self
+                .datetime_slices(&date, calendar)
+                .into_iter()
+                .map(|(_, end)| end)
+                .filter(|end| end <= dt)
+                .max();
+            if found.is_some() {
+                return found;
+            }
+            date = yesterday(&date);
+        }
+        None
+    }
+
+    /// 距离当前时段结束还有多长时间, 精确到纳秒, 比如10:15休息或15:00收盘,
+    /// 夜盘跨零点时计算到次日凌晨的结束时间, 不在时段内时返回None
+    pub fn time_to_slice_end(
+        &self,
+        dt: &MyDateTimeType,
+        calendar: Option<&TradingCalendar>,
+    ) -> Option<Duration> {
+        self.current_slice(dt, calendar)
+            .map(|(_, end)| duration_between(dt, &end))
+    }
+
+    /// 距离dt所属交易日的收盘(即最后一个时段结束)还有多长时间, 精确到纳秒,
+    /// 夜盘时计算到下一个交易日的收盘, 已经收盘时返回None
+    pub fn time_to_day_end(
+        &self,
+        dt: &MyDateTimeType,
+        calendar: Option<&TradingCalendar>,
+    ) -> Option<Duration> {
+        let default = TradingCalendar::new();
+        let calendar = calendar.unwrap_or(&default);
+        let trading_day = self.trading_day(dt, Some(calendar));
+        self.datetime_slices(&trading_day, calendar)
+            .into_iter()
+            .map(|(_, end)| end)
+            .max()
+            .filter(|end| end > dt)
+            .map(|end| duration_between(dt, &end))
+    }
+
+    /// start到end之间的交易时长, 仅累计时段内的时间, 跳过盘中休息、周末及节假日, 精确到纳秒,
+    /// calendar为None时仅跳过周末, start不早于end时为0
+    pub fn trading_duration_between(
+        &self,
+        start: &MyDateTimeType,
+        end: &MyDateTimeType,
+        calendar: Option<&TradingCalendar>,
+    ) -> Duration {
+        let mut total = Duration::ZERO;
+        if start >= end {
+            return total;
+        }
+        let default = TradingCalendar::new();
+        let calendar = calendar.unwrap_or(&default);
+        let last = self.shifted_date(end);
+        let mut date = self.shifted_date(start);
+        while date <= last {
+            for (begin, finish) in self.datetime_slices(&date, calendar) {
+                let begin = begin.max(*start);
+                let finish = finish.min(*end);
+                if begin < finish {
+                    total += duration_between(&begin, &finish);
+                }
+            }
+            date = tomorrow(&date);
+        }
+        total
+    }
+
+    /// trading_duration_between的逆运算, 从start开始经过duration交易时长之后的日期时间点,
+    /// 跳过盘中休息、周末及节假日, 比如商品期货11:20之后30个交易分钟是13:50,
+    /// 恰好用完一个时段时返回该时段的结束时间, calendar为None时仅跳过周末,
+    /// 没有任何时段、duration超出i64纳秒或MAX_SCAN_DAYS天内用不完时返回None
+    pub fn add_trading_time(
+        &self,
+        start: &MyDateTimeType,
+        duration: Duration,
+        calendar: Option<&TradingCalendar>,
+    ) -> Option<MyDateTimeType> {
+        if self.slices.is_empty() {
+            return None;
+        }
+        let mut remaining = i64::try_from(duration.as_nanos()).ok()?;
+        if remaining == 0 {
+            return Some(*start);
+        }
+        let default = TradingCalendar::new();
+        let calendar = calendar.unwrap_or(&default);
+        let mut date = self.shifted_date(start);
+        for _ in 0..MAX_SCAN_DAYS {
+            for (begin, finish) in self.datetime_slices(&date, calendar) {
+                if finish <= *start {
+                    continue;
+                }
+                let begin = begin.max(*start);
+                let available =
+                    datetime_to_timestamp_nanos(&finish) - datetime_to_timestamp_nanos(&begin);
+                if remaining <= available {
+                    return Some(datetime_add_nanos(&begin, remaining));
+                }
+                remaining -= available;
+            }
+            date = tomorrow(&date);
+        }
+        None
+    }
+
+    /// 从dt所在的shifted日期开始往后逐日查找, pick选出时段的开始或结束时间, 取大于dt的最小值
+    fn scan_forward<F>(
+        &self,
+        dt: &MyDateTimeType,
+        calendar: Option<&TradingCalendar>,
+        pick: F,
+    ) -> Option<MyDateTimeType>
+    where
+        F: Fn((MyDateTimeType, MyDateTimeType)) -> MyDateTimeType,
+    {
+        if self.slices.is_empty() {
+            return None;
+        }
+        let default = TradingCalendar::new();
+        let calendar = calendar.unwrap_or(&default);
+        let mut date = self.shifted_date(dt);
+        for _ in 0..MAX_SCAN_DAYS {
+            let found = self
+                .datetime_slices(&date, calendar)
+                .into_iter()
+                .map(&pick)
+                .filter(|tm| tm > dt)
+                .min();
+            if found.is_some() {
+                return found;
+            }
+            date = tomorrow(&date);
+        }
+        None
+    }
+
+    /// shifted日期(即平移之后的日期)为date的各时段的实际起止时间,
+    /// 夜盘从前一天的anchor(默认20:00)开始计算, 已按日历排除休市的时段
+    pub(crate) fn datetime_slices(
+        &self,
+        date: &MyDateType,
+        calendar: &TradingCalendar,
+    ) -> Vec<(MyDateTimeType, MyDateTimeType)> {
+        let to_midnight = (SECS_IN_ONE_DAY - self.anchor) % SECS_IN_ONE_DAY;
+        let anchor = datetime_add_nanos(
+            &date_at_hms(date, 0, 0, 0),
+            -(to_midnight as i64) * NANOS_PER_SEC,
+        );
+        let night_end = self.night_end();
+        let has_night = self.has_night_on(date, calendar);
+        let is_trading_day = calendar.is_trading_day(date);
+        self.slices
+            .iter()
+            .filter(|slice| {
+                if slice.begin.seconds() < night_end {
+                    has_night
+                } else {
+                    is_trading_day
+                }
+            })
+            .map(|slice| {
+                let begin =
+                    datetime_add_nanos(&anchor, slice.begin.seconds() as i64 * NANOS_PER_SEC);
+                let end = datetime_add_nanos(&anchor, slice.end.seconds() as i64 * NANOS_PER_SEC);
+                (begin, end)
+            })
+            .collect()
+    }
+
+    /// 日期时间点所属的交易日, 时间平移之后的日期如果不是交易日, 则顺延到下一个交易日,
+    /// 比如周五22:30及周六00:30都属于下周一, 节假日前最后一个交易日晚上属于节后第一个交易日,
+    /// calendar为None时仅跳过周末
+    pub fn trading_day(
+        &self,
+        dt: &MyDateTimeType,
+        calendar: Option<&TradingCalendar>,
+    ) -> MyDateType {
+        let default = TradingCalendar::new();
+        let calendar = calendar.unwrap_or(&default);
+        let date = self.shifted_date(dt);
+        if calendar.is_trading_day(&date) {
+            date
+        } else {
+            calendar.next_trading_day(&date)
+        }
+    }
+
     /// 所有add_slice调用完毕之后，应该调用post_fix进行整合
     pub fn add_slice_directly(&mut self, slice: SessionSlice) -> &mut Self {
+        self.bitmap.invalidate();
         self.slices.push(slice);
         self
     }
 
-    /// 输入的是原始时间，尚未平移, 注意：结束时间应大于开始时间(除非开始时间大于20点且结束时间跨零点)
+    /// 输入的是原始时间，尚未平移, 注意：结束时间应大于开始时间(除非开始时间不早于anchor且结束时间跨零点)
+    /// 结束时间等于anchor时视为交易日结束, 比如anchor为0:00时add_slice(0, 0, 0, 0)为全天交易
     /// 所有add_slice调用完毕之后，应该调用post_fix进行整合
     pub fn add_slice(
         &mut self,
@@ -415,11 +1021,18 @@ impl TradeSession {
         end_hour: u32,
         end_minute: u32,
     ) -> Result<()> {
-        self.slices.push(SessionSlice::new_from_time(
-            start_hour,
-            start_minute,
-            end_hour,
-            end_minute,
+        let shift = |hour, minute| {
+            ShiftedTime::new_from_midnight_seconds_anchored(hour * 3600 + minute * 60, self.anchor)
+        };
+        let mut end = shift(end_hour, end_minute);
+        if end.seconds() == 0 {
+            end = ShiftedTime(SECS_IN_ONE_DAY);
+        }
+        self.bitmap.invalidate();
+        self.slices.push(SessionSlice::new_from_shifted_anchored(
+            shift(start_hour, start_minute),
+            end,
+            self.anchor,
         )?);
         Ok(())
     }
@@ -434,35 +1047,59 @@ impl TradeSession {
 
         let first = self.slices.first().expect("no fail");
         let last = self.slices.last().expect("no fail");
-        self.day_begin = first.begin.into();
-        self.day_end = last.end.into();
+        self.day_begin = first.begin.nominal_time_anchored(self.anchor);
+        self.day_end = last.end.nominal_time_anchored(self.anchor);
 
-        // 6:00 shift后(6+4)*3600 = 36000, 11:00 shift后54000
+        // 按实际时间在6:00到11:00之间开始
         let morning = self.slices.iter().find(|slice| {
-            let secs = slice.begin.seconds();
-            secs >= 36000 && secs < 54000
+            let secs = slice.begin.nominal_seconds_anchored(self.anchor);
+            (6 * 3600..11 * 3600).contains(&secs)
         });
         if let Some(slice) = morning {
-            self.morning_begin = slice.begin.into();
+            self.morning_begin = slice.begin.nominal_time_anchored(self.anchor);
         } else {
-            self.morning_begin = self.day_begin.clone();
+            self.morning_begin = self.day_begin;
         }
     }
 
-    /// 在所有Slice都加入之后，使用minutes方式重算，合并并移除重叠等，计算day_begin、day_end的值
+    /// 在所有Slice都加入之后，按秒排序，合并相连或重叠的时段，计算day_begin、day_end的值,
+    /// 不是整分钟的开始结束时间(比如11:29:30)保持不变, anchor不同的slice会按本session的anchor重新平移
     pub fn post_fix(&mut self) {
         if self.slices.is_empty() {
+            self.bitmap.invalidate();
             return;
         }
-        let minutes = self.minutes_list();
-        self.internal_load_minutes(&minutes);
+        let anchor = self.anchor;
+        self.slices = self
+            .slices
+            .iter()
+            .flat_map(|slice| slice.rebase(anchor))
+            .collect();
+        self.slices.sort_by_key(|slice| slice.begin);
+        let mut merged: Vec<SessionSlice> = Vec::with_capacity(self.slices.len());
+        for slice in self.slices.drain(..) {
+            match merged.last_mut() {
+                Some(last) if slice.begin <= last.end => last.end = last.end.max(slice.end),
+                _ => merged.push(slice),
+            }
+        }
+        self.slices = merged;
+        self.build_bitmap();
         self.fix_day_begin_end();
     }
 
-    /// 获取此时间片对应分钟(u32)的数组，含开始，不含结束
+    /// 时段已排序并合并之后生成位图
+    fn build_bitmap(&mut self) {
+        let ranges = self
+            .slices
+            .iter()
+            .map(|s| (s.begin.seconds(), s.end.seconds()));
+        self.bitmap = SecondBitmap::new(ranges);
+    }
+
+    /// 获取此时间片对应分钟(u32)的数组，含开始，不含结束, 参看SessionSlice::minutes_list
     /// 注意：所有数值超前4小时
-    /// 应用场景1：校验所有add_slice，自动移除重迭，自动排序，参看post_fix
-    /// 应用场景2：比如仅交易了5个品种，要检查这些品种开市时间段有行情，用以求这些Session的并集
+    /// 应用场景：比如仅交易了5个品种，要检查这些品种开市时间段有行情, 求并集可直接用union
     pub fn minutes_list(&self) -> BTreeSet<u16> {
         self.slices
             .iter()
@@ -476,6 +1113,7 @@ impl TradeSession {
     {
         let minutes: BTreeSet<u16> = minutes.into_iter().map(|t| t.into()).collect();
         self.internal_load_minutes(&minutes);
+        self.build_bitmap();
         self.fix_day_begin_end();
     }
 
@@ -502,6 +1140,7 @@ impl TradeSession {
                     self.slices.push(SessionSlice {
                         begin: ShiftedTime(start as u32 * 60),
                         end: ShiftedTime(prev as u32 * 60 + 60),
+                        anchor: self.anchor,
                     });
                     current_start = Some(minute);
                     prev_minute = Some(minute);
@@ -518,11 +1157,52 @@ impl TradeSession {
             self.slices.push(SessionSlice {
                 begin: ShiftedTime(start as u32 * 60),
                 end: ShiftedTime(end as u32 * 60 + 60),
+                anchor: self.anchor,
             });
         }
     }
 }
 
+impl TradeSession {
+    /// 平移之后的日期, 即anchor(默认20:00)之后算作下一个自然日
+    pub(crate) fn shifted_date(&self, dt: &MyDateTimeType) -> MyDateType {
+        let date = dt.date();
+        if self.anchor > 0
+            && time_to_midnight_nanos(&dt.time()) >= self.anchor as i64 * NANOS_PER_SEC
+        {
+            tomorrow(&date)
+        } else {
+            date
+        }
+    }
+
+    /// dt所在的日期是否开市, 夜盘(含跨零点部分)要求夜盘开始的那天晚上确实有夜盘,
+    /// 其余时间要求当天是交易日, calendar为None时仅排除周末
+    fn is_open_date(&self, dt: &MyDateTimeType, calendar: Option<&TradingCalendar>) -> bool {
+        let default = TradingCalendar::new();
+        let calendar = calendar.unwrap_or(&default);
+        if self.shift(&dt.time()).seconds() < self.night_end() {
+            self.has_night_on(&self.shifted_date(dt), calendar)
+        } else {
+            calendar.is_trading_day(&dt.date())
+        }
+    }
+
+    /// shifted日期为date的夜盘是否开市, 夜盘开始于前一个自然日晚上, 参看NightRule
+    fn has_night_on(&self, date: &MyDateType, calendar: &TradingCalendar) -> bool {
+        match self.night_rule {
+            NightRule::AfterTradingDay => calendar.has_night_session(&yesterday(date)),
+            NightRule::BeforeTradingDay => calendar.is_trading_day(date),
+        }
+    }
+}
+
+impl Default for TradeSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Display for TradeSession {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         #[cfg(feature = "with-chrono")]
@@ -552,10 +1232,29 @@ impl Display for TradeSession {
     }
 }
 
-/// 将数据库中的Session字段转为Vec<SessionSlice>,
+/// 从start到end的时长, end必须不小于start
+fn duration_between(start: &MyDateTimeType, end: &MyDateTimeType) -> Duration {
+    let nanos = datetime_to_timestamp_nanos(end) - datetime_to_timestamp_nanos(start);
+    Duration::from_nanos(nanos as u64)
+}
+
+/// 时段的开始结束时间, "%H:%M:%S"或"%H:%M", 保留秒数
+pub(crate) fn parse_slice_time(time_str: &str) -> Result<MyTimeType> {
+    parse_time(time_str, "%H:%M:%S").or_else(|_| parse_time(time_str, "%H:%M"))
+}
+
+/// 将数据库中的Session字段转为Vec<SessionSlice>, 时间精确到秒,
 /// [{"Begin":"09:00:00","End":"10:15:00"},{"Begin":"10:30:00","End":"11:30:00"},{"Begin":"13:30:00","End":"15:00:00"},{"Begin":"21:00:00","End":"02:30:00"}]
 pub fn parse_json_slices(json: &str) -> Result<Vec<SessionSlice>> {
-    let mut res = Vec::<SessionSlice>::new();
+    parse_json_times(json)?
+        .iter()
+        .map(|(begin, end)| SessionSlice::new(begin, end))
+        .collect()
+}
+
+/// 参看parse_json_slices, 返回各时段的开始结束时间, 尚未构造SessionSlice
+pub(crate) fn parse_json_times(json: &str) -> Result<Vec<(MyTimeType, MyTimeType)>> {
+    let mut res = vec![];
     match serde_json::from_str(&json.to_lowercase())? {
         Value::Array(arr) => {
             for elem in arr {
@@ -563,24 +1262,34 @@ pub fn parse_json_slices(json: &str) -> Result<Vec<SessionSlice>> {
                 // println!("{} ~ {}", elem["Begin"], elem["End"]);
                 match (&elem["begin"], &elem["end"]) {
                     (Value::String(bb), Value::String(ee)) => {
-                        let begin = parse_time(bb, "%H:%M:%S")?;
-                        let end = parse_time(ee, "%H:%M:%S")?;
-                        res.push(SessionSlice::new(&begin, &end)?);
+                        let begin = parse_slice_time(bb)?;
+                        let end = parse_slice_time(ee)?;
+                        res.push((begin, end));
+                    }
+                    _ => {
+                        return Err(TradeSessionError::JsonFormat(format!(
+                            "trade session解析错误: {}",
+                            elem
+                        )));
                     }
-                    _ => return Err(anyhow!("trade session解析错误: {}", elem)),
                 }
             }
         }
-        _ => return Err(anyhow!("trade session字符串必须是Array类型")),
+        _ => {
+            return Err(TradeSessionError::JsonFormat(
+                "trade session字符串必须是Array类型".to_string(),
+            ));
+        }
     }
 
-    return Ok(res);
+    Ok(res)
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::phase::OrderHandling;
 
     #[test]
     fn slice_to_minutes() -> anyhow::Result<()> {
@@ -602,7 +1311,7 @@ mod tests {
         let minutes2 = session.minutes_list();
         println!("slice minutes2: {:?}", minutes2);
 
-        assert_eq!(minutes == minutes2, true);
+        assert_eq!(minutes, minutes2);
         assert_eq!(session.slices.len(), 2);
         for slice in &session.slices {
             println!("{}", slice);
@@ -663,4 +1372,459 @@ mod tests {
         let end = time_from_midnight_nanos(nanos_since_midnight_end);
         println!("start {}, end {}", start, end);
     }
+
+    #[test]
+    fn in_session_with_date() {
+        let session = TradeSession::new_commodity_session_night();
+        let fri = make_date(2025, 7, 25);
+        let sat = make_date(2025, 7, 26);
+        let sun = make_date(2025, 7, 27);
+        let mon = make_date(2025, 7, 28);
+        assert!(session.in_session_at(&date_at_hms(&fri, 21, 0, 0), true, false, None));
+        assert!(session.in_session_at(&date_at_hms(&sat, 1, 0, 0), true, false, None));
+        assert!(!session.in_session_at(&date_at_hms(&sat, 2, 30, 0), true, false, None));
+        assert!(session.in_session_at(&date_at_hms(&sat, 2, 30, 0), true, true, None));
+        assert!(!session.in_session_at(&date_at_hms(&sun, 10, 0, 0), true, false, None));
+        assert!(!session.in_session_at(&date_at_hms(&sun, 21, 0, 0), true, false, None));
+        assert!(!session.in_session_at(&date_at_hms(&mon, 1, 0, 0), true, false, None));
+
+        // 周一是假日, 周五晚上没有夜盘
+        let calendar = TradingCalendar::new_from_holidays([mon]);
+        let calendar = Some(&calendar);
+        assert!(!session.in_session_at(&date_at_hms(&fri, 21, 0, 0), true, false, calendar));
+        assert!(!session.in_session_at(&date_at_hms(&sat, 1, 0, 0), true, false, calendar));
+        assert!(!session.in_session_at(&date_at_hms(&mon, 9, 0, 0), true, false, calendar));
+        assert!(session.in_session_at(&date_at_hms(&fri, 9, 0, 0), true, false, calendar));
+
+        let start = date_at_hms(&sat, 3, 0, 0);
+        let end = date_at_hms(&mon, 8, 59, 0);
+        assert!(!session.any_in_session_between(&start, &end, true, None));
+        let end = date_at_hms(&mon, 9, 0, 0);
+        assert!(session.any_in_session_between(&start, &end, true, None));
+        assert!(!session.any_in_session_between(&start, &end, false, None));
+        assert!(!session.any_in_session_between(&end, &start, true, None));
+        let start = date_at_hms(&fri, 15, 0, 0);
+        assert!(session.any_in_session_between(&start, &end, false, None));
+        assert!(!session.any_in_session_between(&start, &end, false, calendar));
+    }
+
+    #[test]
+    fn next_open_close() {
+        let session = TradeSession::new_commodity_session_night();
+        let fri = make_date(2025, 7, 25);
+        let sat = make_date(2025, 7, 26);
+        let mon = make_date(2025, 7, 28);
+        let tue = make_date(2025, 7, 29);
+
+        let dt = date_at_hms(&fri, 10, 20, 0);
+        assert_eq!(session.current_slice(&dt, None), None);
+        assert_eq!(
+            session.next_open(&dt, None),
+            Some(date_at_hms(&fri, 10, 30, 0))
+        );
+        assert_eq!(
+            session.next_close(&dt, None),
+            Some(date_at_hms(&fri, 11, 30, 0))
+        );
+        assert_eq!(
+            session.prev_close(&dt, None),
+            Some(date_at_hms(&fri, 10, 15, 0))
+        );
+
+        // 周五夜盘跨零点
+        let dt = date_at_hms(&fri, 23, 0, 0);
+        assert_eq!(
+            session.current_slice(&dt, None),
+            Some((date_at_hms(&fri, 21, 0, 0), date_at_hms(&sat, 2, 30, 0)))
+        );
+        assert_eq!(
+            session.next_close(&dt, None),
+            Some(date_at_hms(&sat, 2, 30, 0))
+        );
+        assert_eq!(
+            session.next_open(&dt, None),
+            Some(date_at_hms(&mon, 9, 0, 0))
+        );
+        assert_eq!(
+            session.prev_close(&dt, None),
+            Some(date_at_hms(&fri, 15, 0, 0))
+        );
+
+        // 周末
+        let dt = date_at_hms(&sat, 12, 0, 0);
+        assert_eq!(
+            session.next_open(&dt, None),
+            Some(date_at_hms(&mon, 9, 0, 0))
+        );
+        assert_eq!(
+            session.prev_close(&dt, None),
+            Some(date_at_hms(&sat, 2, 30, 0))
+        );
+        let dt = date_at_hms(&mon, 15, 0, 0);
+        assert_eq!(session.prev_close(&dt, None), Some(dt));
+        assert_eq!(
+            session.next_open(&dt, None),
+            Some(date_at_hms(&mon, 21, 0, 0))
+        );
+        assert_eq!(
+            session.next_close(&dt, None),
+            Some(date_at_hms(&tue, 2, 30, 0))
+        );
+
+        // 周一是假日
+        let calendar = TradingCalendar::new_from_holidays([mon]);
+        let dt = date_at_hms(&fri, 16, 0, 0);
+        assert_eq!(
+            session.next_open(&dt, Some(&calendar)),
+            Some(date_at_hms(&tue, 9, 0, 0))
+        );
+        let dt = date_at_hms(&tue, 8, 0, 0);
+        assert_eq!(
+            session.prev_close(&dt, Some(&calendar)),
+            Some(date_at_hms(&fri, 15, 0, 0))
+        );
+
+        assert_eq!(TradeSession::new().next_open(&dt, None), None);
+
+        // 日历全部休市时最多查找MAX_SCAN_DAYS天
+        let first = make_date(2000, 1, 1);
+        let closed = TradingCalendar::new_from_holidays(
+            (0..2 * MAX_SCAN_DAYS as i32 + 400)
+                .map(|days| date_from_days_since_epoch(date_to_days_since_epoch(&first) + days)),
+        );
+        let dt = date_at_hms(&make_date(2010, 6, 1), 12, 0, 0);
+        assert_eq!(session.next_open(&dt, Some(&closed)), None);
+        assert_eq!(session.next_close(&dt, Some(&closed)), None);
+        assert_eq!(session.prev_close(&dt, Some(&closed)), None);
+    }
+
+    #[test]
+    fn time_to_end() {
+        let session = TradeSession::new_commodity_session_night();
+        let fri = make_date(2025, 7, 25);
+        let sat = make_date(2025, 7, 26);
+        let mon = make_date(2025, 7, 28);
+
+        let dt = datetime_add_nanos(&date_at_hms(&fri, 10, 14, 59), 250_000_000);
+        assert_eq!(
+            session.time_to_slice_end(&dt, None),
+            Some(Duration::from_millis(750))
+        );
+        assert_eq!(
+            session.time_to_day_end(&dt, None),
+            Some(Duration::from_secs(4 * 3600 + 45 * 60 + 1) - Duration::from_millis(250))
+        );
+        let dt = date_at_hms(&fri, 10, 20, 0);
+        assert_eq!(session.time_to_slice_end(&dt, None), None);
+        assert_eq!(
+            session.time_to_day_end(&dt, None),
+            Some(Duration::from_secs(4 * 3600 + 40 * 60))
+        );
+        assert_eq!(
+            session.time_to_day_end(&date_at_hms(&fri, 15, 0, 0), None),
+            None
+        );
+
+        // 夜盘跨零点, 收盘是下周一15:00
+        let dt = date_at_hms(&fri, 23, 30, 0);
+        assert_eq!(
+            session.time_to_slice_end(&dt, None),
+            Some(Duration::from_secs(3 * 3600))
+        );
+        let day_end = date_at_hms(&mon, 15, 0, 0);
+        assert_eq!(
+            session.time_to_day_end(&dt, None),
+            Some(duration_between(&dt, &day_end))
+        );
+        let dt = date_at_hms(&sat, 2, 0, 0);
+        assert_eq!(
+            session.time_to_slice_end(&dt, None),
+            Some(Duration::from_secs(30 * 60))
+        );
+    }
+
+    #[test]
+    fn trading_phase() {
+        let t = |h, m| make_time(h, m, 0);
+
+        let night = TradeSession::new_commodity_session_night();
+        assert_eq!(night.phase_at(&t(20, 50)), TradingPhase::Closed);
+        assert_eq!(night.phase_at(&t(20, 55)), TradingPhase::PreOpenAuction);
+        assert_eq!(night.phase_at(&t(20, 59)), TradingPhase::AuctionMatch);
+        assert_eq!(night.phase_at(&t(21, 0)), TradingPhase::Continuous);
+        assert_eq!(night.phase_at(&t(3, 0)), TradingPhase::Closed);
+        // 有夜盘的品种早盘开盘前没有集合竞价
+        assert_eq!(night.phase_at(&t(8, 57)), TradingPhase::Closed);
+        assert_eq!(night.phase_at(&t(10, 20)), TradingPhase::Break);
+        assert_eq!(night.phase_at(&t(15, 0)), TradingPhase::Closed);
+
+        let day = TradeSession::new_commodity_session();
+        assert_eq!(day.phase_at(&t(8, 55)), TradingPhase::PreOpenAuction);
+        assert_eq!(day.phase_at(&t(8, 59)), TradingPhase::AuctionMatch);
+
+        let stock = TradeSession::new_stock_session();
+        assert_eq!(stock.phase_at(&t(9, 20)), TradingPhase::PreOpenAuction);
+        assert_eq!(stock.phase_at(&t(9, 27)), TradingPhase::PreOpenQueue);
+        assert_eq!(
+            stock.phase_at(&t(9, 27)).order_handling(),
+            OrderHandling::Queued
+        );
+        assert_eq!(
+            day.phase_at(&t(8, 59)).order_handling(),
+            OrderHandling::Rejected
+        );
+        assert_eq!(stock.phase_at(&t(12, 0)), TradingPhase::Break);
+        assert_eq!(stock.phase_at(&t(14, 58)), TradingPhase::ClosingAuction);
+        assert_eq!(
+            stock.phase_at(&t(14, 58)).order_handling(),
+            OrderHandling::Queued
+        );
+
+        // 周六晚上没有夜盘, 周一上午休息时为Break
+        let sat = make_date(2025, 7, 26);
+        let mon = make_date(2025, 7, 28);
+        assert_eq!(
+            night.phase_at_datetime(&date_at_hms(&sat, 20, 56, 0), None),
+            TradingPhase::Closed
+        );
+        assert_eq!(
+            night.phase_at_datetime(&date_at_hms(&mon, 10, 20, 0), None),
+            TradingPhase::Break
+        );
+    }
+
+    #[test]
+    fn trading_duration() {
+        let session = TradeSession::new_commodity_session_night();
+        let fri = make_date(2025, 7, 25);
+        let mon = make_date(2025, 7, 28);
+
+        // 跨越午休, 11:20~13:40只有20分钟
+        let start = date_at_hms(&fri, 11, 20, 0);
+        assert_eq!(
+            session.trading_duration_between(&start, &date_at_hms(&fri, 13, 40, 0), None),
+            Duration::from_secs(20 * 60)
+        );
+        // 周五收盘到下周一开盘前, 只有周五夜盘
+        assert_eq!(
+            session.trading_duration_between(
+                &date_at_hms(&fri, 15, 0, 0),
+                &date_at_hms(&mon, 9, 0, 0),
+                None
+            ),
+            Duration::from_secs(5 * 3600 + 30 * 60)
+        );
+        // 一整个交易日
+        assert_eq!(
+            session.trading_duration_between(
+                &date_at_hms(&fri, 15, 0, 0),
+                &date_at_hms(&mon, 15, 0, 0),
+                None
+            ),
+            Duration::from_secs((330 + 225) * 60)
+        );
+        // 周五晚上是节假日前, 没有夜盘
+        let calendar = TradingCalendar::new_from_holidays([mon]);
+        assert_eq!(
+            session.trading_duration_between(
+                &date_at_hms(&fri, 15, 0, 0),
+                &date_at_hms(&make_date(2025, 7, 29), 9, 0, 0),
+                Some(&calendar)
+            ),
+            Duration::ZERO
+        );
+        assert_eq!(
+            session.trading_duration_between(&start, &start, None),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn add_trading_time() {
+        let session = TradeSession::new_commodity_session_night();
+        let fri = make_date(2025, 7, 25);
+        let mon = make_date(2025, 7, 28);
+
+        let start = date_at_hms(&fri, 11, 20, 0);
+        let minutes = |n: u64| Duration::from_secs(n * 60);
+        assert_eq!(
+            session.add_trading_time(&start, minutes(30), None),
+            Some(date_at_hms(&fri, 13, 50, 0))
+        );
+        assert_eq!(
+            session.add_trading_time(&start, minutes(10), None),
+            Some(date_at_hms(&fri, 11, 30, 0))
+        );
+        assert_eq!(
+            session.add_trading_time(&start, Duration::ZERO, None),
+            Some(start)
+        );
+        // 收盘之后, 从夜盘开始算, 周五夜盘到周六02:30, 然后是下周一早盘
+        let close = date_at_hms(&fri, 15, 0, 0);
+        assert_eq!(
+            session.add_trading_time(&close, minutes(60), None),
+            Some(date_at_hms(&fri, 22, 0, 0))
+        );
+        assert_eq!(
+            session.add_trading_time(&close, minutes(330 + 15), None),
+            Some(date_at_hms(&mon, 9, 15, 0))
+        );
+        // 与trading_duration_between互逆
+        let end = session
+            .add_trading_time(&start, minutes(500), None)
+            .unwrap();
+        assert_eq!(
+            session.trading_duration_between(&start, &end, None),
+            minutes(500)
+        );
+        assert_eq!(
+            TradeSession::new().add_trading_time(&start, minutes(1), None),
+            None
+        );
+        // 超出i64纳秒, 或MAX_SCAN_DAYS天内用不完
+        assert_eq!(session.add_trading_time(&start, Duration::MAX, None), None);
+        assert_eq!(
+            session.add_trading_time(&start, Duration::from_secs(100 * 365 * 86400), None),
+            None
+        );
+    }
+
+    #[test]
+    fn second_precision() -> Result<()> {
+        let json = r#"[{"Begin":"08:59:30","End":"10:15:00"},{"Begin":"10:30","End":"11:29:30"},{"Begin":"11:00:00","End":"11:29:45"}]"#;
+        let session = TradeSession::new_from_slices(&parse_json_slices(json)?);
+        assert_eq!(session.get_slices().len(), 2);
+        assert_eq!(*session.day_begin(), make_time(8, 59, 30));
+        assert_eq!(*session.day_end(), make_time(11, 29, 45));
+        assert!(session.in_session(&make_time(8, 59, 40), true, false));
+        assert!(!session.in_session(&make_time(11, 29, 50), true, false));
+        // minutes_list包含不完整的分钟
+        let minutes = session.minutes_list();
+        let minute_of = |h, m| (ShiftedTime::new_from_time(h, m).seconds() / 60) as u16;
+        assert!(minutes.contains(&minute_of(8, 59)));
+        assert!(minutes.contains(&minute_of(11, 29)));
+
+        let ends = session.minute_bar_ends();
+        assert_eq!(ends[0], make_time(9, 0, 0));
+        assert_eq!(ends.last(), Some(&make_time(11, 29, 45)));
+        Ok(())
+    }
+
+    #[test]
+    fn custom_anchor() -> Result<()> {
+        // CME Globex, 17:00开始, 次日16:00结束
+        let mut cme = TradeSession::new_with_anchor(&make_time(17, 0, 0));
+        cme.add_slice(17, 0, 16, 0)?;
+        cme.post_fix();
+        assert_eq!(cme.anchor(), make_time(17, 0, 0));
+        assert_eq!(*cme.day_begin(), make_time(17, 0, 0));
+        assert_eq!(*cme.day_end(), make_time(16, 0, 0));
+        assert!(cme.in_session(&make_time(20, 30, 0), true, false));
+        assert!(cme.in_session(&make_time(9, 0, 0), true, false));
+        assert!(!cme.in_session(&make_time(16, 30, 0), true, false));
+        assert!(cme.get_slices()[0].is_night());
+        // 2025-07-22周二18:00属于周三
+        let dt = date_at_hms(&make_date(2025, 7, 22), 18, 0, 0);
+        assert_eq!(cme.trading_day(&dt, None), make_date(2025, 7, 23));
+        assert!(
+            SessionSlice::new_anchored(&make_time(19, 0, 0), &make_time(21, 0, 0), 17 * 3600)
+                .is_ok()
+        );
+        // 结束于anchor时视为交易日结束, 同add_slice
+        let slice =
+            SessionSlice::new_anchored(&make_time(16, 0, 0), &make_time(17, 0, 0), 17 * 3600)?;
+        assert_eq!(slice.end(), ShiftedTime(SECS_IN_ONE_DAY));
+        assert!(SessionSlice::new(&make_time(19, 0, 0), &make_time(20, 0, 0)).is_ok());
+
+        // 默认anchor的16:00~18:00跨越17:00, 重新平移后拆分为两段
+        let slice = SessionSlice::new(&make_time(16, 0, 0), &make_time(18, 0, 0))?;
+        let parts = slice.rebase(17 * 3600);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].begin(), ShiftedTime(0));
+        assert_eq!(parts[0].end(), ShiftedTime(3600));
+        assert_eq!(parts[1].begin(), ShiftedTime(23 * 3600));
+
+        // 加密货币全天交易, 0:00开始
+        let mut crypto = TradeSession::new_with_anchor(&make_time(0, 0, 0));
+        crypto.add_slice(0, 0, 0, 0)?;
+        crypto.post_fix();
+        assert!(!crypto.has_night());
+        assert!(crypto.in_session(&make_time(23, 59, 59), true, false));
+        let dt = date_at_hms(&make_date(2025, 7, 22), 23, 0, 0);
+        assert_eq!(crypto.trading_day(&dt, None), make_date(2025, 7, 22));
+        assert_eq!(crypto.minute_bars(false).len(), 1440);
+        Ok(())
+    }
+
+    #[test]
+    fn in_session_bitmap() -> Result<()> {
+        let json = r#"[{"Begin":"08:59:30","End":"10:15:00"},{"Begin":"10:30","End":"11:29:30"}]"#;
+        let mut cme = TradeSession::new_with_anchor(&make_time(17, 0, 0));
+        cme.add_slice(17, 0, 16, 0)?;
+        cme.post_fix();
+        let mut crypto = TradeSession::new_with_anchor(&make_time(0, 0, 0));
+        crypto.add_slice(0, 0, 0, 0)?;
+        crypto.post_fix();
+        let sessions = [
+            TradeSession::new_commodity_session_night(),
+            TradeSession::new_stock_index_session(),
+            TradeSession::new_full_session(),
+            TradeSession::new_from_slices(&parse_json_slices(json)?),
+            TradeSession::new_from_minutes(TradeSession::new_stock_session().minutes_list()),
+            cme,
+            crypto,
+            TradeSession::new(),
+        ];
+        let flags = [(true, true), (true, false), (false, true), (false, false)];
+        for session in &sessions {
+            // 每一秒及其后的半秒(归于后一秒), 查表与逐个时段比较的结果相同
+            for nanos in (0..SECS_IN_ONE_DAY as i64 * NANOS_PER_SEC).step_by(500_000_000) {
+                let ts = time_from_midnight_nanos(nanos);
+                for (begin, end) in flags {
+                    assert_eq!(
+                        session.in_session(&ts, begin, end),
+                        session.in_session_scan(&ts, begin, end),
+                        "{ts} {begin} {end} {:?}",
+                        session.get_slices()
+                    );
+                }
+            }
+        }
+
+        // 修改时段之后未调用post_fix时, 逐个时段比较
+        let mut session = TradeSession::new_stock_session();
+        assert!(session.in_session(&make_time(11, 30, 0), true, true));
+        session.get_slices_mut()[0] = SessionSlice::new_from_time(9, 30, 11, 0)?;
+        assert!(!session.in_session(&make_time(11, 30, 0), true, true));
+        assert!(session.in_session(&make_time(11, 0, 0), false, true));
+        assert!(!session.in_session(&make_time(11, 0, 0), false, false));
+        Ok(())
+    }
+
+    #[test]
+    fn in_session_batch() {
+        let session = TradeSession::new_commodity_session_night();
+        let at = |h: i64, m: i64, nanos: i64| (h * 3600 + m * 60) * NANOS_PER_SEC + nanos;
+        // 2025-07-22 10:15:00的epoch纳秒数
+        let epoch = datetime_to_timestamp_nanos(&date_at_hms(&make_date(2025, 7, 22), 10, 15, 0));
+        let ts = [
+            at(21, 0, 0),
+            at(10, 15, 0),
+            at(10, 14, 999_999_999),
+            at(2, 30, 1),
+            epoch,
+        ];
+        for (begin, end) in [(true, true), (true, false), (false, true), (false, false)] {
+            let expected: Vec<bool> = ts
+                .iter()
+                .map(|&nanos| session.in_session(&time_from_midnight_nanos(nanos), begin, end))
+                .collect();
+            assert_eq!(session.in_session_batch(&ts, begin, end), expected);
+        }
+        assert_eq!(
+            session.in_session_batch(&ts, true, false),
+            vec![true, false, true, false, false]
+        );
+        assert!(session.in_session_batch(&[], true, false).is_empty());
+    }
 }
@@ -0,0 +1,398 @@
+use std::fmt::Display;
+use std::io::Read;
+use std::path::Path;
+
+use encoding_rs_io::DecodeReaderBytes;
+
+use crate::calendar::{open_csv, parse_csv_date};
+use crate::error::{Result, TradeSessionError};
+use crate::jcswitch::*;
+use crate::session_mgr::{SessionManager, record_layout};
+use crate::tradesession::{
+    DEFAULT_ANCHOR, SECS_IN_ONE_DAY, SessionSlice, ShiftedTime, TradeSession, parse_json_times,
+};
+
+/// 交易时段定义中可疑的地方, 时间均为实际时间
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SessionIssue {
+    /// 两个时段重叠, post_fix会将其合并, 参数为两个时段的开始和结束时间
+    Overlapping((MyTimeType, MyTimeType), (MyTimeType, MyTimeType)),
+    /// 开始时间等于结束时间的时段, 无法构造SessionSlice
+    ZeroLength(MyTimeType),
+    /// 在anchor(默认20:00)之前开始且跨越anchor的时段, 比如19:00~23:00, 无法用ShiftedTime表示
+    CrossesAnchor(MyTimeType, MyTimeType),
+    /// 其他无法构造SessionSlice的时段, 比如开始结束颠倒的10:00~09:00, 参数为开始、结束时间及错误信息
+    InvalidSlice(MyTimeType, MyTimeType, String),
+    /// 夜盘时段不在21:00开始, SessionSlice::is_night及集合竞价都假定夜盘21:00开始
+    NightNotAt21(MyTimeType),
+    /// 没有在6:00~11:00之间开始的早盘时段, morning_begin退化为day_begin
+    NoMorning,
+    /// 日盘结束时间早于早盘开始时间, 参数为(day_end, morning_begin)
+    DayEndBeforeMorning(MyTimeType, MyTimeType),
+    /// 无法解析的定义, 参数为错误信息
+    Malformed(String),
+}
+
+impl Display for SessionIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionIssue::Overlapping((b1, e1), (b2, e2)) => {
+                write!(
+                    f,
+                    "slices {b1}~{e1} and {b2}~{e2} overlap and will be merged"
+                )
+            }
+            SessionIssue::ZeroLength(at) => write!(f, "zero-length slice at {at}"),
+            SessionIssue::CrossesAnchor(begin, end) => {
+                write!(f, "slice {begin}~{end} crosses the trading day anchor")
+            }
+            SessionIssue::InvalidSlice(begin, end, msg) => {
+                write!(f, "invalid slice {begin}~{end}: {msg}")
+            }
+            SessionIssue::NightNotAt21(at) => {
+                write!(f, "night slice starts at {at}, not 21:00")
+            }
+            SessionIssue::NoMorning => {
+                write!(f, "no morning slice, morning_begin falls back to day_begin")
+            }
+            SessionIssue::DayEndBeforeMorning(day_end, morning) => {
+                write!(f, "day_end {day_end} is before morning_begin {morning}")
+            }
+            SessionIssue::Malformed(msg) => write!(f, "malformed: {msg}"),
+        }
+    }
+}
+
+impl TradeSession {
+    /// 检查可疑的定义, 已经post_fix的session中重叠的时段已被合并, 无法报告Overlapping,
+    /// 需要时请用validate_json_slices或lint_csv检查原始定义
+    pub fn validate(&self) -> Vec<SessionIssue> {
+        let anchor = self.anchor_seconds();
+        let nominal = |sec: ShiftedTime| sec.nominal_time_anchored(anchor);
+        let span = |slice: &SessionSlice| (nominal(slice.begin()), nominal(slice.end()));
+        let mut issues = vec![];
+
+        let mut slices: Vec<SessionSlice> = self
+            .get_slices()
+            .iter()
+            .flat_map(|slice| slice.rebase(anchor))
+            .collect();
+        slices.sort_by_key(|slice| slice.begin());
+        // 与之前结束最晚的时段比较, 一个长时段可能与后面的多个时段重叠
+        let mut latest: Option<&SessionSlice> = None;
+        for slice in &slices {
+            match latest {
+                Some(prev) if slice.begin() < prev.end() => {
+                    issues.push(SessionIssue::Overlapping(span(prev), span(slice)));
+                    if slice.end() > prev.end() {
+                        latest = Some(slice);
+                    }
+                }
+                _ => latest = Some(slice),
+            }
+        }
+
+        if anchor == DEFAULT_ANCHOR {
+            let night_open = make_time(21, 0, 0);
+            issues.extend(
+                slices
+                    .iter()
+                    .filter(|slice| slice.is_night() && nominal(slice.begin()) != night_open)
+                    .map(|slice| SessionIssue::NightNotAt21(nominal(slice.begin()))),
+            );
+        }
+
+        if slices.is_empty() {
+            return issues;
+        }
+        // 同fix_day_begin_end
+        let has_morning = slices.iter().any(|slice| {
+            let secs = slice.begin().nominal_seconds_anchored(anchor);
+            (6 * 3600..11 * 3600).contains(&secs)
+        });
+        if !has_morning {
+            // morning_begin退化为day_begin(一般是夜盘开始时间), 不再比较day_end
+            issues.push(SessionIssue::NoMorning);
+            return issues;
+        }
+        let mut fixed = self.clone();
+        fixed.post_fix();
+        if fixed.day_end() < fixed.morning_begin() {
+            issues.push(SessionIssue::DayEndBeforeMorning(
+                *fixed.day_end(),
+                *fixed.morning_begin(),
+            ));
+        }
+        issues
+    }
+}
+
+/// 检查json格式(参看parse_json_slices)的原始时段定义, 可以发现post_fix合并之前的重叠时段,
+/// 以及无法构造SessionSlice的时段, 无法解析时返回Malformed
+pub fn validate_json_slices(json: &str) -> Vec<SessionIssue> {
+    let times = match parse_json_times(json) {
+        Ok(times) => times,
+        Err(err) => return vec![SessionIssue::Malformed(err.to_string())],
+    };
+    let mut issues = vec![];
+    let mut session = TradeSession::new();
+    for (begin, end) in times {
+        match SessionSlice::new(&begin, &end) {
+            Ok(slice) => {
+                session.add_slice_directly(slice);
+            }
+            Err(_) if begin == end => issues.push(SessionIssue::ZeroLength(begin)),
+            Err(_) if crosses_anchor(&begin, &end) => {
+                issues.push(SessionIssue::CrossesAnchor(begin, end))
+            }
+            Err(err) => issues.push(SessionIssue::InvalidSlice(begin, end, err.to_string())),
+        }
+    }
+    issues.extend(session.validate());
+    issues
+}
+
+/// begin < DEFAULT_ANCHOR <= end吗? 下午或晚上开始、次日上午结束的时段视为跨零点, end加一天,
+/// 其他结束早于开始的时段(比如10:00~09:00)视为开始结束颠倒, 不算跨越anchor
+fn crosses_anchor(begin: &MyTimeType, end: &MyTimeType) -> bool {
+    let secs = |t: &MyTimeType| ShiftedTime::from_time_anchored(t, 0).seconds();
+    let noon = 12 * 3600;
+    let begin = secs(begin);
+    let mut end = secs(end);
+    if end < begin && begin >= noon && end < noon {
+        end += SECS_IN_ONE_DAY;
+    }
+    begin < DEFAULT_ANCHOR && DEFAULT_ANCHOR <= end
+}
+
+/// lint报告的一项
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionLint {
+    product: String,
+    /// 交易所未知时为空字符串
+    exchange: String,
+    effective_from: Option<MyDateType>,
+    /// csv文件的行号, 不是从csv文件检查时为None
+    line: Option<u64>,
+    issue: SessionIssue,
+}
+
+impl SessionLint {
+    pub fn product(&self) -> &str {
+        &self.product
+    }
+    /// 交易所未知时为空字符串
+    pub fn exchange(&self) -> &str {
+        &self.exchange
+    }
+    /// 有问题的版本的生效日期
+    pub fn effective_from(&self) -> Option<&MyDateType> {
+        self.effective_from.as_ref()
+    }
+    /// csv文件的行号(从1开始), 不是从csv文件检查时为None
+    pub fn line(&self) -> Option<u64> {
+        self.line
+    }
+    pub fn issue(&self) -> &SessionIssue {
+        &self.issue
+    }
+}
+
+impl Display for SessionLint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {line}: ")?;
+        }
+        if !self.exchange.is_empty() {
+            write!(f, "{}.", self.exchange)?;
+        }
+        write!(f, "{}", self.product)?;
+        if let Some(date) = &self.effective_from {
+            write!(f, " (from {date})")?;
+        }
+        write!(f, ": {}", self.issue)
+    }
+}
+
+impl SessionManager {
+    /// 检查所有品种的所有版本, 按(交易所, 品种, 生效日期)排序,
+    /// 加载时重叠的时段已被合并, 需要时请用lint_csv检查原始的csv文件
+    pub fn lint(&self) -> Vec<SessionLint> {
+        let mut records: Vec<_> = self.records().collect();
+        records.sort_by_key(|record| (record.exchange(), record.product()));
+        records
+            .into_iter()
+            .flat_map(|record| {
+                record.history().iter().flat_map(|(date, session)| {
+                    session.validate().into_iter().map(|issue| SessionLint {
+                        product: record.product().to_string(),
+                        exchange: record.exchange().to_string(),
+                        effective_from: *date,
+                        line: None,
+                        issue,
+                    })
+                })
+            })
+            .collect()
+    }
+}
+
+/// 检查csv文件(格式参看load_records_from_read)的每一行, 按行号排序,
+/// 无法解析的行报告为Malformed, 仅在读取失败时返回错误
+pub fn lint_records_from_read<R: Read>(read: R) -> Result<Vec<SessionLint>> {
+    let mut lints = vec![];
+    let mut rdr = csv::Reader::from_reader(read);
+    for result in rdr.records() {
+        let record = match result.map_err(TradeSessionError::from) {
+            Ok(record) => record,
+            Err(TradeSessionError::Io(err)) => return Err(TradeSessionError::Io(err)),
+            Err(err) => {
+                lints.push(SessionLint {
+                    product: String::new(),
+                    exchange: String::new(),
+                    effective_from: None,
+                    line: Some(csv_line(&err)),
+                    issue: SessionIssue::Malformed(err.to_string()),
+                });
+                continue;
+            }
+        };
+        let line = record.position().map(|pos| pos.line());
+        let (key_idx, exchange_idx, json_idx, date_idx) = match record_layout(&record) {
+            Ok(layout) => layout,
+            Err(err) => {
+                lints.push(SessionLint {
+                    product: String::new(),
+                    exchange: String::new(),
+                    effective_from: None,
+                    line,
+                    issue: SessionIssue::Malformed(err.to_string()),
+                });
+                continue;
+            }
+        };
+        let mut issues = vec![];
+        let effective_from = match date_idx.map(|idx| record[idx].trim()) {
+            Some(date_str) if !date_str.is_empty() => match parse_csv_date(date_str) {
+                Ok(date) => Some(date),
+                Err(err) => {
+                    issues.push(SessionIssue::Malformed(err.to_string()));
+                    None
+                }
+            },
+            _ => None,
+        };
+        issues.extend(validate_json_slices(&record[json_idx]));
+        lints.extend(issues.into_iter().map(|issue| {
+            SessionLint {
+                product: record[key_idx].to_string(),
+                exchange: exchange_idx
+                    .map_or("", |idx| record[idx].trim())
+                    .to_string(),
+                effective_from,
+                line,
+                issue,
+            }
+        }));
+    }
+    Ok(lints)
+}
+
+/// 参看lint_records_from_read
+pub fn lint_csv<P: AsRef<Path>>(csv_file_path: P) -> Result<Vec<SessionLint>> {
+    let file = open_csv(csv_file_path.as_ref())?;
+    lint_records_from_read(DecodeReaderBytes::new(file))
+}
+
+/// 参看lint_records_from_read
+pub fn lint_csv_content(csv_content: &str) -> Result<Vec<SessionLint>> {
+    lint_records_from_read(csv_content.as_bytes())
+}
+
+fn csv_line(err: &TradeSessionError) -> u64 {
+    match err {
+        TradeSessionError::CsvFormat { line, .. } => *line,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn validate_and_lint() -> anyhow::Result<()> {
+        assert!(
+            TradeSession::new_commodity_session_night()
+                .validate()
+                .is_empty()
+        );
+        assert!(
+            TradeSession::new_stock_index_session()
+                .validate()
+                .is_empty()
+        );
+
+        let issues = validate_json_slices(
+            r#"[{"Begin":"19:00","End":"23:00"},{"Begin":"21:30","End":"23:00"},
+            {"Begin":"13:00","End":"15:00"},{"Begin":"14:00","End":"14:30"},
+            {"Begin":"10:00","End":"10:00"},{"Begin":"18:00","End":"01:00"},
+            {"Begin":"11:00","End":"10:30"},{"Begin":"19:00","End":"20:00"}]"#,
+        );
+        let hm = |h, m| make_time(h, m, 0);
+        let reversed = SessionSlice::new(&hm(11, 0), &hm(10, 30)).unwrap_err();
+        assert_eq!(
+            issues,
+            vec![
+                SessionIssue::CrossesAnchor(hm(19, 0), hm(23, 0)),
+                SessionIssue::ZeroLength(hm(10, 0)),
+                SessionIssue::CrossesAnchor(hm(18, 0), hm(1, 0)),
+                SessionIssue::InvalidSlice(hm(11, 0), hm(10, 30), reversed.to_string()),
+                SessionIssue::Overlapping((hm(13, 0), hm(15, 0)), (hm(14, 0), hm(14, 30))),
+                SessionIssue::NightNotAt21(hm(21, 30)),
+                SessionIssue::NoMorning,
+            ]
+        );
+        // anchor为0:00时, 到24:00结束的夜盘使day_end早于morning_begin
+        let mut midnight = TradeSession::new_with_anchor(&hm(0, 0));
+        midnight.add_slice(9, 0, 15, 0)?;
+        midnight.add_slice(21, 0, 0, 0)?;
+        midnight.post_fix();
+        assert_eq!(
+            midnight.validate(),
+            vec![SessionIssue::DayEndBeforeMorning(hm(0, 0), hm(9, 0))]
+        );
+
+        let csv = "product,exchange,session,date\n\
+            ag,SHFE,\"[{\"\"Begin\"\":\"\"21:00\"\",\"\"End\"\":\"\"02:30\"\"},{\"\"Begin\"\":\"\"09:00\"\",\"\"End\"\":\"\"11:30\"\"}]\",\n\
+            CF,CZCE,\"[{\"\"Begin\"\":\"\"09:00\"\",\"\"End\"\":\"\"10:30\"\"},{\"\"Begin\"\":\"\"10:00\"\",\"\"End\"\":\"\"11:30\"\"}]\",20250101\n\
+            SR,CZCE,\"[]\",2025-13-01\n\
+            xx,DCE\n";
+        let lints = lint_csv_content(csv)?;
+        let lines: Vec<String> = lints.iter().map(|lint| lint.to_string()).collect();
+        assert_eq!(lints.len(), 3, "{lines:#?}");
+        assert_eq!(lints[0].line(), Some(3));
+        assert_eq!(lints[0].product(), "CF");
+        assert!(matches!(lints[0].issue(), SessionIssue::Overlapping(..)));
+        assert_eq!(
+            lines[0],
+            "line 3: CZCE.CF (from 2025-01-01): slices 09:00:00~10:30:00 and 10:00:00~11:30:00 overlap and will be merged"
+        );
+        assert!(matches!(lints[1].issue(), SessionIssue::Malformed(_)));
+        assert_eq!(lints[2].line(), Some(5));
+
+        // 加载后重叠的时段已合并, lint只能发现其他问题
+        let mut s_mgr = SessionManager::new_from_csv_content(include_str!("../tradesession.csv"))?;
+        assert!(s_mgr.lint().is_empty(), "{:#?}", s_mgr.lint());
+        let mut evening = TradeSession::new();
+        evening.add_slice(21, 30, 23, 0)?;
+        evening.post_fix();
+        s_mgr.add_session("zz", evening);
+        let lints = s_mgr.lint();
+        assert_eq!(lints.len(), 2);
+        assert_eq!(lints[0].product(), "zz");
+        assert_eq!(lints[0].line(), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,314 @@
+use std::time::Duration;
+
+use crate::calendar::TradingCalendar;
+use crate::jcswitch::*;
+use crate::phase::TradingPhase;
+use crate::tradesession::{MAX_SCAN_DAYS, TradeSession};
+
+/// 带时区的查询, 先把时间点转为本session时区的当地时间, 再按当地时间查询,
+/// 没有设置时区(参看TradeSession::set_time_zone)时使用时间点自带的时区,
+/// 返回的时间点使用同一时区, 时长按实际经过的时间计算, 夏令时切换当天可能多或少一个小时
+impl TradeSession {
+    /// 时间点在本session时区的当地日期时间
+    pub fn local_datetime(&self, zoned: &MyZonedType) -> MyDateTimeType {
+        zoned_to_local(zoned, &self.zone_for(zoned))
+    }
+
+    /// 一个时间点, 按交易日历可以交易吗? 参看is_tradable
+    pub fn is_tradable_zoned(
+        &self,
+        zoned: &MyZonedType,
+        calendar: Option<&TradingCalendar>,
+    ) -> bool {
+        self.is_tradable(&self.local_datetime(zoned), calendar)
+    }
+
+    /// 一个时间点, 在时段内吗? 参看in_session_at
+    pub fn in_session_at_zoned(
+        &self,
+        zoned: &MyZonedType,
+        include_begin: bool,
+        include_end: bool,
+        calendar: Option<&TradingCalendar>,
+    ) -> bool {
+        self.in_session_at(
+            &self.local_datetime(zoned),
+            include_begin,
+            include_end,
+            calendar,
+        )
+    }
+
+    /// 一个时间点所处的交易阶段, 参看phase_at_datetime
+    pub fn phase_at_zoned(
+        &self,
+        zoned: &MyZonedType,
+        calendar: Option<&TradingCalendar>,
+    ) -> TradingPhase {
+        self.phase_at_datetime(&self.local_datetime(zoned), calendar)
+    }
+
+    /// 一个时间点所属的交易日, 参看trading_day
+    pub fn trading_day_zoned(
+        &self,
+        zoned: &MyZonedType,
+        calendar: Option<&TradingCalendar>,
+    ) -> MyDateType {
+        self.trading_day(&self.local_datetime(zoned), calendar)
+    }
+
+    /// 时间点所在时段的起止时间点, 参看current_slice
+    pub fn current_slice_zoned(
+        &self,
+        zoned: &MyZonedType,
+        calendar: Option<&TradingCalendar>,
+    ) -> Option<(MyZonedType, MyZonedType)> {
+        let tz = self.zone_for(zoned);
+        self.current_slice(&zoned_to_local(zoned, &tz), calendar)
+            .map(|(begin, end)| (local_to_zoned(&begin, &tz), local_to_zoned(&end, &tz)))
+    }
+
+    /// 时间点之后的下一个时段开始时间点, 参看next_open
+    pub fn next_open_zoned(
+        &self,
+        zoned: &MyZonedType,
+        calendar: Option<&TradingCalendar>,
+    ) -> Option<MyZonedType> {
+        let tz = self.zone_for(zoned);
+        self.next_open(&zoned_to_local(zoned, &tz), calendar)
+            .map(|dt| local_to_zoned(&dt, &tz))
+    }
+
+    /// 时间点之后的下一个时段结束时间点, 参看next_close
+    pub fn next_close_zoned(
+        &self,
+        zoned: &MyZonedType,
+        calendar: Option<&TradingCalendar>,
+    ) -> Option<MyZonedType> {
+        let tz = self.zone_for(zoned);
+        self.next_close(&zoned_to_local(zoned, &tz), calendar)
+            .map(|dt| local_to_zoned(&dt, &tz))
+    }
+
+    /// 时间点之前(含)的最近一个时段结束时间点, 参看prev_close
+    pub fn prev_close_zoned(
+        &self,
+        zoned: &MyZonedType,
+        calendar: Option<&TradingCalendar>,
+    ) -> Option<MyZonedType> {
+        let tz = self.zone_for(zoned);
+        self.prev_close(&zoned_to_local(zoned, &tz), calendar)
+            .map(|dt| local_to_zoned(&dt, &tz))
+    }
+
+    /// 距离当前时段结束实际还有多长时间, 不在时段内时返回None, 参看time_to_slice_end
+    pub fn time_to_slice_end_zoned(
+        &self,
+        zoned: &MyZonedType,
+        calendar: Option<&TradingCalendar>,
+    ) -> Option<Duration> {
+        self.current_slice_zoned(zoned, calendar).map(|(_, end)| {
+            let nanos = zoned_to_timestamp_nanos(&end) - zoned_to_timestamp_nanos(zoned);
+            Duration::from_nanos(nanos as u64)
+        })
+    }
+
+    /// start到end之间实际经过的交易时长, 参看trading_duration_between,
+    /// 时段的起止按当地时间计算, 跨越夏令时切换时也不会多算或少算一个小时
+    pub fn trading_duration_between_zoned(
+        &self,
+        start: &MyZonedType,
+        end: &MyZonedType,
+        calendar: Option<&TradingCalendar>,
+    ) -> Duration {
+        let mut total = Duration::ZERO;
+        let start_nanos = zoned_to_timestamp_nanos(start);
+        let end_nanos = zoned_to_timestamp_nanos(end);
+        if start_nanos >= end_nanos {
+            return total;
+        }
+        let default = TradingCalendar::new();
+        let calendar = calendar.unwrap_or(&default);
+        let tz = self.zone_for(start);
+        let last = self.shifted_date(&zoned_to_local(end, &tz));
+        let mut date = self.shifted_date(&zoned_to_local(start, &tz));
+        while date <= last {
+            for (begin, finish) in self.zoned_slices(&date, calendar, &tz) {
+                let begin = begin.max(start_nanos);
+                let finish = finish.min(end_nanos);
+                if begin < finish {
+                    total += Duration::from_nanos((finish - begin) as u64);
+                }
+            }
+            date = tomorrow(&date);
+        }
+        total
+    }
+
+    /// 从start开始实际经过duration交易时长之后的时间点, 参看add_trading_time,
+    /// duration超出i64纳秒或MAX_SCAN_DAYS天内用不完时返回None
+    pub fn add_trading_time_zoned(
+        &self,
+        start: &MyZonedType,
+        duration: Duration,
+        calendar: Option<&TradingCalendar>,
+    ) -> Option<MyZonedType> {
+        if self.get_slices().is_empty() {
+            return None;
+        }
+        let tz = self.zone_for(start);
+        let start_nanos = zoned_to_timestamp_nanos(start);
+        let mut remaining = i64::try_from(duration.as_nanos()).ok()?;
+        if remaining == 0 {
+            return Some(zoned_from_timestamp_nanos(start_nanos, &tz));
+        }
+        let default = TradingCalendar::new();
+        let calendar = calendar.unwrap_or(&default);
+        let mut date = self.shifted_date(&zoned_to_local(start, &tz));
+        for _ in 0..MAX_SCAN_DAYS {
+            for (begin, finish) in self.zoned_slices(&date, calendar, &tz) {
+                if finish <= start_nanos {
+                    continue;
+                }
+                let begin = begin.max(start_nanos);
+                let available = finish - begin;
+                if remaining <= available {
+                    return Some(zoned_from_timestamp_nanos(begin + remaining, &tz));
+                }
+                remaining -= available;
+            }
+            date = tomorrow(&date);
+        }
+        None
+    }
+
+    /// 本session的时区, 没有设置时使用时间点自带的时区
+    fn zone_for(&self, zoned: &MyZonedType) -> MyTimeZone {
+        self.time_zone()
+            .cloned()
+            .unwrap_or_else(|| zoned_time_zone(zoned))
+    }
+
+    /// 参看datetime_slices, 起止时间为时区tz中的时间点, 用UTC纳秒数表示
+    fn zoned_slices(
+        &self,
+        date: &MyDateType,
+        calendar: &TradingCalendar,
+        tz: &MyTimeZone,
+    ) -> Vec<(i64, i64)> {
+        let nanos = |dt: &MyDateTimeType| zoned_to_timestamp_nanos(&local_to_zoned(dt, tz));
+        self.datetime_slices(date, calendar)
+            .iter()
+            .map(|(begin, end)| (nanos(begin), nanos(end)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::tradesession::NightRule;
+    use anyhow::Result;
+
+    /// CME Globex, 芝加哥时间17:00开始, 次日16:00结束, 周日晚上开市
+    fn cme_session() -> Result<TradeSession> {
+        let mut session = TradeSession::new_with_anchor(&make_time(17, 0, 0));
+        session.add_slice(17, 0, 16, 0)?;
+        session.post_fix();
+        session
+            .set_time_zone(&parse_time_zone("America/Chicago")?)
+            .set_night_rule(NightRule::BeforeTradingDay);
+        Ok(session)
+    }
+
+    /// UTC时间点
+    fn utc(date: MyDateType, hour: u32, minute: u32) -> Result<MyZonedType> {
+        let utc = parse_time_zone("UTC")?;
+        Ok(local_to_zoned(&date_at_hms(&date, hour, minute, 0), &utc))
+    }
+
+    fn same_instant(a: &MyZonedType, b: &MyZonedType) -> bool {
+        zoned_to_timestamp_nanos(a) == zoned_to_timestamp_nanos(b)
+    }
+
+    #[test]
+    fn dst_transitions() -> Result<()> {
+        let cme = cme_session()?;
+        // 2025-03-09(周日)美国开始夏令时, 之前芝加哥为UTC-6, 之后为UTC-5
+        // 周四22:30 UTC是16:30 CST, 休市; 23:30 UTC是17:30 CST, 开盘
+        let thursday = make_date(2025, 3, 6);
+        assert!(!cme.is_tradable_zoned(&utc(thursday, 22, 30)?, None));
+        assert!(cme.is_tradable_zoned(&utc(thursday, 23, 30)?, None));
+        assert_eq!(
+            cme.local_datetime(&utc(thursday, 23, 30)?),
+            date_at_hms(&thursday, 17, 30, 0)
+        );
+        // 周五晚上休市, 周日22:30 UTC是17:30 CDT, 开盘, 属于周一
+        assert!(!cme.is_tradable_zoned(&utc(make_date(2025, 3, 7), 23, 30)?, None));
+        let sunday = make_date(2025, 3, 9);
+        assert!(cme.is_tradable_zoned(&utc(sunday, 22, 30)?, None));
+        assert_eq!(
+            cme.trading_day_zoned(&utc(sunday, 22, 30)?, None),
+            make_date(2025, 3, 10)
+        );
+        // 周五16:00收盘之后, 下一次开盘是周日17:00 CDT, 即22:00 UTC
+        let open = cme
+            .next_open_zoned(&utc(make_date(2025, 3, 7), 22, 30)?, None)
+            .unwrap();
+        assert!(same_instant(&open, &utc(sunday, 22, 0)?));
+        assert_eq!(time_zone_name(&zoned_time_zone(&open)), "America/Chicago");
+        // 周二21:30 UTC是16:30 CDT, 休市
+        assert!(!cme.is_tradable_zoned(&utc(make_date(2025, 3, 11), 21, 30)?, None));
+
+        // Eurex 08:00~22:00法兰克福时间, 2025-03-30(周日)开始夏令时, UTC+1变为UTC+2
+        let mut eurex = TradeSession::new_with_anchor(&make_time(0, 0, 0));
+        eurex.add_slice(8, 0, 22, 0)?;
+        eurex.post_fix();
+        eurex.set_time_zone(&parse_time_zone("Europe/Berlin")?);
+        assert!(!eurex.is_tradable_zoned(&utc(make_date(2025, 3, 28), 6, 30)?, None));
+        assert!(eurex.is_tradable_zoned(&utc(make_date(2025, 3, 31), 6, 30)?, None));
+        assert!(eurex.is_tradable_zoned(&utc(make_date(2025, 3, 28), 20, 30)?, None));
+        assert!(!eurex.is_tradable_zoned(&utc(make_date(2025, 3, 31), 20, 30)?, None));
+        Ok(())
+    }
+
+    #[test]
+    fn dst_durations() -> Result<()> {
+        let cme = cme_session()?;
+        let chicago = parse_time_zone("America/Chicago")?;
+        let at = |date, hour| local_to_zoned(&date_at_hms(&date, hour, 0, 0), &chicago);
+        let friday = make_date(2025, 3, 7);
+        let sunday = make_date(2025, 3, 9);
+        // 周五15:00~16:00, 周日17:00~18:00, 共2个小时, 中间实际经过了48个小时(而非当地时间的49个小时)
+        assert_eq!(
+            cme.trading_duration_between_zoned(&at(friday, 15), &at(sunday, 18), None),
+            Duration::from_secs(2 * 3600)
+        );
+        let end = cme
+            .add_trading_time_zoned(&at(friday, 15), Duration::from_secs(2 * 3600), None)
+            .unwrap();
+        assert!(same_instant(&end, &utc(sunday, 23, 0)?));
+        assert!(
+            cme.add_trading_time_zoned(&at(friday, 15), Duration::MAX, None)
+                .is_none()
+        );
+        // 周日17:00 CDT开盘到周一16:00 CDT收盘
+        let slice = cme.current_slice_zoned(&at(sunday, 18), None).unwrap();
+        assert!(same_instant(&slice.0, &at(sunday, 17)));
+        assert_eq!(
+            cme.time_to_slice_end_zoned(&at(sunday, 18), None),
+            Some(Duration::from_secs(22 * 3600))
+        );
+
+        // 没有设置时区时, 按时间点自带的时区查询, UTC 12:00按12:00查询, 休市
+        let mut session = TradeSession::new_commodity_session_night();
+        let noon = utc(make_date(2025, 7, 22), 12, 0)?;
+        assert!(!session.is_tradable_zoned(&noon, None));
+        // 设置为北京时间后, UTC 12:00是20:00, 13:30是21:30
+        session.set_time_zone(&parse_time_zone("Asia/Shanghai")?);
+        assert_eq!(session.phase_at_zoned(&noon, None), TradingPhase::Closed);
+        assert!(session.is_tradable_zoned(&utc(make_date(2025, 7, 22), 13, 30)?, None));
+        Ok(())
+    }
+}
@@ -1,24 +1,83 @@
-use anyhow::{Result, anyhow};
-use tradesession::{SessionManager, TradeSession};
+use std::fmt::Display;
 
-use tradesession::jcswitch::{time_from_midnight_nanos, time_to_midnight_nanos};
+use tradesession::{SessionManager, TradeSession, TradeSessionError, TradingCalendar};
+
+use tradesession::jcswitch::{
+    datetime_from_timestamp_nanos, parse_date, time_from_midnight_nanos, time_to_midnight_nanos,
+};
+
+use ffi::ErrorCode;
+
+/// 传给c++的错误, 异常信息以错误代码开头, 比如"[6] csv line 3: ...", 参看error_code_of
+pub struct CppError(TradeSessionError);
+
+type Result<T> = std::result::Result<T, CppError>;
+
+impl From<TradeSessionError> for CppError {
+    fn from(err: TradeSessionError) -> Self {
+        Self(err)
+    }
+}
+
+impl Display for CppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = match &self.0 {
+            TradeSessionError::InvalidSlice(_) => ErrorCode::InvalidSlice,
+            TradeSessionError::OverlappingSlice(_) => ErrorCode::OverlappingSlice,
+            TradeSessionError::InvalidAuction(_) => ErrorCode::InvalidAuction,
+            TradeSessionError::TimeFormat(_) => ErrorCode::TimeFormat,
+            TradeSessionError::UnknownTimeZone(_) => ErrorCode::UnknownTimeZone,
+            TradeSessionError::JsonFormat(_) => ErrorCode::JsonFormat,
+            TradeSessionError::CsvFormat { .. } | TradeSessionError::CsvRecord { .. } => {
+                ErrorCode::CsvFormat
+            }
+            TradeSessionError::UnknownProduct(_) => ErrorCode::UnknownProduct,
+            TradeSessionError::Io(_) => ErrorCode::Io,
+            _ => ErrorCode::Unknown,
+        };
+        write!(f, "[{}] {}", code.repr, self.0)
+    }
+}
+
+fn unknown_product(product: &str) -> CppError {
+    CppError(TradeSessionError::UnknownProduct(product.to_string()))
+}
+
+/// 从c++异常信息(e.what())中取得错误代码, 不是本库的异常时返回Unknown
+pub fn error_code_of(what: &str) -> ErrorCode {
+    let repr = what
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .and_then(|(code, _)| code.parse::<u8>().ok());
+    match repr {
+        Some(repr) if repr <= ErrorCode::OverlappingSlice.repr => ErrorCode { repr },
+        _ => ErrorCode::Unknown,
+    }
+}
 
 pub struct SessionPP {
     session: tradesession::TradeSession,
+    /// 节假日, 供in_session_at等使用, 参看set_holidays
+    calendar: Option<TradingCalendar>,
 }
 pub struct SessionMgr {
     mgr: SessionManager,
 }
 
-pub fn new_session() -> Box<SessionPP> {
+fn boxed(session: TradeSession) -> Box<SessionPP> {
     Box::new(SessionPP {
-        session: TradeSession::new(),
+        session,
+        calendar: None,
     })
 }
 
+pub fn new_session() -> Box<SessionPP> {
+    boxed(TradeSession::new())
+}
+
 pub fn new_from_minutes(minutes: Vec<u16>) -> Box<SessionPP> {
     let session = TradeSession::new_from_minutes(minutes);
-    Box::new(SessionPP { session })
+    boxed(session)
 }
 
 pub fn new_mgr() -> Box<SessionMgr> {
@@ -39,32 +98,32 @@ pub fn new_from_csv_content(csv_content: &str) -> Result<Box<SessionMgr>> {
 
 pub fn new_commodity_session() -> Box<SessionPP> {
     let session = TradeSession::new_commodity_session();
-    Box::new(SessionPP { session })
+    boxed(session)
 }
 
 pub fn new_commodity_session_night() -> Box<SessionPP> {
     let session = TradeSession::new_commodity_session_night();
-    Box::new(SessionPP { session })
+    boxed(session)
 }
 
 pub fn new_stock_session() -> Box<SessionPP> {
     let session = TradeSession::new_stock_session();
-    Box::new(SessionPP { session })
+    boxed(session)
 }
 
 pub fn new_stock_index_session() -> Box<SessionPP> {
     let session = TradeSession::new_stock_index_session();
-    Box::new(SessionPP { session })
+    boxed(session)
 }
 
 pub fn new_bond_session() -> Box<SessionPP> {
     let session = TradeSession::new_bond_session();
-    Box::new(SessionPP { session })
+    boxed(session)
 }
 
 pub fn new_full_session() -> Box<SessionPP> {
     let session = TradeSession::new_full_session();
-    Box::new(SessionPP { session })
+    boxed(session)
 }
 
 impl SessionPP {
@@ -101,9 +160,44 @@ impl SessionPP {
         let end = time_from_midnight_nanos(nanos_since_midnight_end);
         self.session.any_in_session(&start, &end, include_begin_end)
     }
+    pub fn in_session_at(
+        &self,
+        nanos_since_epoch: i64,
+        include_begin: bool,
+        include_end: bool,
+    ) -> bool {
+        let dt = datetime_from_timestamp_nanos(nanos_since_epoch);
+        self.session
+            .in_session_at(&dt, include_begin, include_end, self.calendar.as_ref())
+    }
+
+    pub fn any_in_session_between(
+        &self,
+        nanos_since_epoch_start: i64,
+        nanos_since_epoch_end: i64,
+        include_begin_end: bool,
+    ) -> bool {
+        let start = datetime_from_timestamp_nanos(nanos_since_epoch_start);
+        let end = datetime_from_timestamp_nanos(nanos_since_epoch_end);
+        self.session
+            .any_in_session_between(&start, &end, include_begin_end, self.calendar.as_ref())
+    }
+    pub fn set_holidays(&mut self, holidays: &[i32]) -> Result<()> {
+        let dates = holidays
+            .iter()
+            .map(|date| parse_date(&format!("{date:08}"), "%Y%m%d"))
+            .collect::<tradesession::Result<Vec<_>>>()?;
+        self.calendar = if dates.is_empty() {
+            None
+        } else {
+            Some(TradingCalendar::new_from_holidays(dates))
+        };
+        Ok(())
+    }
     pub fn minutes_list(&self) -> Vec<u16> {
-        self.session.minutes_list().iter().map(|tm| *tm).collect()
+        self.session.minutes_list().iter().copied().collect()
     }
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         format!("{}", self.session)
     }
@@ -114,8 +208,9 @@ impl SessionPP {
         end_hour: u32,
         end_minute: u32,
     ) -> Result<()> {
-        self.session
-            .add_slice(start_hour, start_minute, end_hour, end_minute)
+        Ok(self
+            .session
+            .add_slice(start_hour, start_minute, end_hour, end_minute)?)
     }
     pub fn post_fix(&mut self) {
         self.session.post_fix();
@@ -124,10 +219,26 @@ impl SessionPP {
 
 impl SessionMgr {
     pub fn reload_csv_content(&mut self, csv_content: &str, merge: bool) -> Result<()> {
-        self.mgr.reload_csv_content(csv_content, merge)
+        Ok(self.mgr.reload_csv_content(csv_content, merge)?)
     }
     pub fn reload_csv_file(&mut self, csv_file_path: &str, merge: bool) -> Result<()> {
-        self.mgr.reload_csv_file(csv_file_path, merge)
+        Ok(self.mgr.reload_csv_file(csv_file_path, merge)?)
+    }
+
+    pub fn reload_csv_file_lenient(
+        &mut self,
+        csv_file_path: &str,
+        merge: bool,
+    ) -> Result<Vec<String>> {
+        let errors = self.mgr.reload_csv_file_lenient(csv_file_path, merge)?;
+        Ok(errors.iter().map(|e| e.to_string()).collect())
+    }
+
+    pub fn reload_calendar_csv_content(&mut self, csv_content: &str, merge: bool) -> Result<()> {
+        Ok(self.mgr.reload_calendar_csv_content(csv_content, merge)?)
+    }
+    pub fn reload_calendar_csv_file(&mut self, csv_file_path: &str, merge: bool) -> Result<()> {
+        Ok(self.mgr.reload_calendar_csv_file(csv_file_path, merge)?)
     }
 
     pub fn has_session(&self, product: &str) -> bool {
@@ -136,11 +247,20 @@ impl SessionMgr {
 
     pub fn get_session(&self, product: &str) -> Result<Box<SessionPP>> {
         self.mgr
-            .get_session(product)
-            .map(|s| Box::new(SessionPP { session: s.clone() }))
-            .ok_or_else(|| anyhow!("Session for product '{}' not found", product))
+            .try_get_session(product)
+            .map(|s| boxed(s.clone()))
+            .map_err(CppError)
     }
 
+    pub fn get_session_for_instrument(&self, instrument: &str) -> Result<Box<SessionPP>> {
+        self.mgr
+            .try_get_session_for_instrument(instrument)
+            .map(|s| boxed(s.clone()))
+            .map_err(CppError)
+    }
+
+    // cxx要求以Box传递opaque类型
+    #[allow(clippy::boxed_local)]
     pub fn add_session(&mut self, product: &str, session: Box<SessionPP>) {
         self.mgr.add_session(product, session.session);
     }
@@ -148,14 +268,14 @@ impl SessionMgr {
     pub fn day_begin(&self, product: &str) -> Result<i64> {
         self.mgr
             .day_begin(product)
-            .map(|tm| time_to_midnight_nanos(tm))
-            .ok_or_else(|| anyhow!("Day begin for product '{}' not found", product))
+            .map(time_to_midnight_nanos)
+            .ok_or_else(|| unknown_product(product))
     }
 
     pub fn day_end(&self, product: &str) -> Result<i64> {
         match self.mgr.day_end(product) {
             Some(tm) => Ok(time_to_midnight_nanos(tm)),
-            None => Err(anyhow!("Day end for product '{}' not found", product)),
+            None => Err(unknown_product(product)),
         }
     }
 
@@ -172,7 +292,7 @@ impl SessionMgr {
             .in_session(product, &ts, include_begin, include_end)
         {
             Some(b) => Ok(b),
-            None => Err(anyhow!("Session check for product '{}' not found", product)),
+            None => Err(unknown_product(product)),
         }
     }
 
@@ -190,27 +310,78 @@ impl SessionMgr {
             .any_in_session(product, &start, &end, include_begin_end)
         {
             Some(b) => Ok(b),
-            None => Err(anyhow!("Session check for product '{}' not found", product)),
+            None => Err(unknown_product(product)),
+        }
+    }
+    pub fn in_session_at(
+        &self,
+        product: &str,
+        nanos_since_epoch: i64,
+        include_begin: bool,
+        include_end: bool,
+    ) -> Result<bool> {
+        let dt = datetime_from_timestamp_nanos(nanos_since_epoch);
+        match self
+            .mgr
+            .in_session_at(product, &dt, include_begin, include_end)
+        {
+            Some(b) => Ok(b),
+            None => Err(unknown_product(product)),
+        }
+    }
+
+    pub fn any_in_session_between(
+        &self,
+        product: &str,
+        nanos_since_epoch_start: i64,
+        nanos_since_epoch_end: i64,
+        include_begin_end: bool,
+    ) -> Result<bool> {
+        let start = datetime_from_timestamp_nanos(nanos_since_epoch_start);
+        let end = datetime_from_timestamp_nanos(nanos_since_epoch_end);
+        match self
+            .mgr
+            .any_in_session_between(product, &start, &end, include_begin_end)
+        {
+            Some(b) => Ok(b),
+            None => Err(unknown_product(product)),
         }
     }
     pub fn sessions_count(&self) -> usize {
         self.mgr.session_map().len()
     }
     pub fn session_map_keys(&self) -> Vec<String> {
-        self.mgr
-            .session_map()
-            .iter()
-            .map(|(k, _)| k.clone())
-            .collect()
+        self.mgr.session_map().keys().cloned().collect()
     }
 }
 
 #[cxx::bridge(namespace = "rustpp")]
 mod ffi {
+    /// 错误代码, 对应tradesession::TradeSessionError, 异常信息以"[错误代码]"开头
+    #[derive(Debug)]
+    enum ErrorCode {
+        /// 不是本库的异常
+        Unknown = 0,
+        InvalidSlice,
+        InvalidAuction,
+        TimeFormat,
+        UnknownTimeZone,
+        JsonFormat,
+        CsvFormat,
+        UnknownProduct,
+        /// 文件读写错误, 比如文件不存在
+        Io,
+        /// 时段重叠, 放在最后以保持已有错误代码的值不变
+        OverlappingSlice,
+    }
+
     extern "Rust" {
         type SessionPP;
         type SessionMgr;
 
+        /// 从异常信息(e.what())中取得错误代码
+        fn error_code_of(what: &str) -> ErrorCode;
+
         /// c++, new_session(),创建空session
         fn new_session() -> Box<SessionPP>;
         fn new_from_minutes(minutes: Vec<u16>) -> Box<SessionPP>;
@@ -260,6 +431,23 @@ mod ffi {
             nanos_since_midnight_end: i64,
             include_begin_end: bool,
         ) -> bool;
+        /// 某个日期时间点落在session中吗? 排除周末, 以及set_holidays设置的节假日及节假日前的夜盘
+        /// 参数是1970-01-01 00:00:00以来的纳秒数(本地时间, 不是UTC)
+        fn in_session_at(
+            self: &SessionPP,
+            nanos_since_epoch: i64,
+            include_begin: bool,
+            include_end: bool,
+        ) -> bool;
+        /// start,end之间任意日期时间点落在session中吗? 排除周末及set_holidays设置的节假日
+        fn any_in_session_between(
+            self: &SessionPP,
+            nanos_since_epoch_start: i64,
+            nanos_since_epoch_end: i64,
+            include_begin_end: bool,
+        ) -> bool;
+        /// 设置节假日, 格式为yyyymmdd的整数, 比如20251001, 为空时清除, 日期无效时会爆出异常
+        fn set_holidays(self: &mut SessionPP, holidays: &[i32]) -> Result<()>;
         fn add_slice(
             self: &mut SessionPP,
             start_hour: u32,
@@ -267,7 +455,7 @@ mod ffi {
             end_hour: u32,
             end_minute: u32,
         ) -> Result<()>;
-        /// 在所有Slice都加入之后，使用minutes方式重算，合并并移除重叠等，计算day_begin、day_end的值
+        /// 在所有Slice都加入之后，按秒排序，合并相连或重叠的时段，计算day_begin、day_end的值, 不是整分钟的开始结束时间(比如11:29:30)保持不变, anchor不同的slice会按本session的anchor重新平移
         fn post_fix(self: &mut SessionPP);
 
         /////////////////////////////////////////////////////
@@ -281,9 +469,32 @@ mod ffi {
         /// 注意sessions列,(json里面有逗号,需要多重双引号)
         /// ag,SHFE,"[{""Begin"":""09:00:00"",""End"":""10:15:00""},{""Begin"":""10:30:00"",""End"":""11:30:00""},{""Begin"":""13:30:00"",""End"":""15:00:00""},{""Begin"":""21:00:00"",""End"":""02:30:00""}]"
         fn reload_csv_file(self: &mut SessionMgr, csv_file_path: &str, merge: bool) -> Result<()>;
+        /// 宽松模式, 跳过有错误的行, 返回各行的错误信息(含行号及品种), 仅在读取失败时爆出异常
+        fn reload_csv_file_lenient(
+            self: &mut SessionMgr,
+            csv_file_path: &str,
+            merge: bool,
+        ) -> Result<Vec<String>>;
+        /// 节假日csv, 两列 exchange,date, exchange为`*`表示所有交易所通用
+        fn reload_calendar_csv_content(
+            self: &mut SessionMgr,
+            csv_content: &str,
+            merge: bool,
+        ) -> Result<()>;
+        /// 节假日csv, 两列 exchange,date, exchange为`*`表示所有交易所通用
+        fn reload_calendar_csv_file(
+            self: &mut SessionMgr,
+            csv_file_path: &str,
+            merge: bool,
+        ) -> Result<()>;
         fn has_session(self: &SessionMgr, product: &str) -> bool;
         /// 获取失败时会爆出异常
         fn get_session(self: &SessionMgr, product: &str) -> Result<Box<SessionPP>>;
+        /// 用合约代码获取session, 比如ag2412, SR501, m2501-C-3000, 获取失败时会爆出异常
+        fn get_session_for_instrument(
+            self: &SessionMgr,
+            instrument: &str,
+        ) -> Result<Box<SessionPP>>;
         /// 如果旧数据存在，会被替换
         fn add_session(self: &mut SessionMgr, product: &str, session: Box<SessionPP>);
         /// 获取失败时会爆出异常
@@ -306,6 +517,23 @@ mod ffi {
             nanos_since_midnight_end: i64,
             include_begin_end: bool,
         ) -> Result<bool>;
+        /// 排除周末、节假日及节假日前的夜盘, 获取失败时会爆出异常
+        /// 参数是1970-01-01 00:00:00以来的纳秒数(本地时间, 不是UTC)
+        fn in_session_at(
+            self: &SessionMgr,
+            product: &str,
+            nanos_since_epoch: i64,
+            include_begin: bool,
+            include_end: bool,
+        ) -> Result<bool>;
+        /// 排除周末、节假日及节假日前的夜盘, 获取失败时会爆出异常
+        fn any_in_session_between(
+            self: &SessionMgr,
+            product: &str,
+            nanos_since_epoch_start: i64,
+            nanos_since_epoch_end: i64,
+            include_begin_end: bool,
+        ) -> Result<bool>;
         fn sessions_count(self: &SessionMgr) -> usize;
         /// cxx crate 目前不支持返回字典，所以只返回keys
         fn session_map_keys(self: &SessionMgr) -> Vec<String>;
@@ -30,12 +30,27 @@ bool tradesession::add_slice(unsigned start_hour, unsigned start_minute,
     box_->add_slice(start_hour, start_minute, end_hour, end_minute);
     return true;
   } catch (const exception &e) {
+    last_error_ = error_code_of(e);
     error = "tradesession: add_slice failed, " + string(e.what());
     std::cerr << error << endl;
     return false;
   }
 }
 
+bool tradesession::set_holidays(const vector<int32_t> &holidays,
+                                string &error) {
+  try {
+    box_->set_holidays(
+        ::rust::Slice<const int32_t>(holidays.data(), holidays.size()));
+    return true;
+  } catch (const exception &e) {
+    last_error_ = error_code_of(e);
+    error = "tradesession: set_holidays failed, " + string(e.what());
+    std::cerr << error << endl;
+    return false;
+  }
+}
+
 //////////////////////////////////////////////////////////////////////////
 
 optional<tradesession> session_mgr::get_session(const string &product) {
@@ -43,18 +58,50 @@ optional<tradesession> session_mgr::get_session(const string &product) {
     auto session = box_->get_session(product);
     return make_optional<tradesession>(std::move(session));
   } catch (const exception &e) {
+    last_error_ = error_code_of(e);
     cerr << "session_mgr: error getting session for product '" << product
          << "': " << e.what() << endl;
     return nullopt;
   }
 };
 
+optional<tradesession>
+session_mgr::get_session_for_instrument(const string &instrument) {
+  try {
+    auto session = box_->get_session_for_instrument(instrument);
+    return make_optional<tradesession>(std::move(session));
+  } catch (const exception &e) {
+    last_error_ = error_code_of(e);
+    cerr << "session_mgr: error getting session for instrument '"
+         << instrument << "': " << e.what() << endl;
+    return nullopt;
+  }
+}
+
 bool session_mgr::load_from_csv(const string &csv_file_path, string &error,
                                 bool merge) {
   try {
     box_->reload_csv_file(csv_file_path, merge);
     return true;
   } catch (const exception &e) {
+    last_error_ = error_code_of(e);
+    error = "session_mgr: failed to load session from CSV: " + string(e.what());
+    cerr << error << endl;
+    return false;
+  }
+}
+
+bool session_mgr::load_from_csv_lenient(const string &csv_file_path,
+                                        vector<string> &row_errors,
+                                        string &error, bool merge) {
+  try {
+    auto errors = box_->reload_csv_file_lenient(csv_file_path, merge);
+    row_errors.clear();
+    for (auto &e : errors)
+      row_errors.emplace_back(string(e));
+    return true;
+  } catch (const exception &e) {
+    last_error_ = error_code_of(e);
     error = "session_mgr: failed to load session from CSV: " + string(e.what());
     cerr << error << endl;
     return false;
@@ -67,6 +114,7 @@ bool session_mgr::load_from_csv_content(const string &csv_content,
     box_->reload_csv_content(csv_content, merge);
     return true;
   } catch (const exception &e) {
+    last_error_ = error_code_of(e);
     error = "session_mgr: failed to load session from CSV content: " +
             string(e.what());
     cerr << error << endl;
@@ -74,4 +122,62 @@ bool session_mgr::load_from_csv_content(const string &csv_content,
   }
 }
 
+bool session_mgr::load_calendar_csv(const string &csv_file_path,
+                                    string &error, bool merge) {
+  try {
+    box_->reload_calendar_csv_file(csv_file_path, merge);
+    return true;
+  } catch (const exception &e) {
+    last_error_ = error_code_of(e);
+    error =
+        "session_mgr: failed to load calendar from CSV: " + string(e.what());
+    cerr << error << endl;
+    return false;
+  }
+}
+
+bool session_mgr::load_calendar_csv_content(const string &csv_content,
+                                            string &error, bool merge) {
+  try {
+    box_->reload_calendar_csv_content(csv_content, merge);
+    return true;
+  } catch (const exception &e) {
+    last_error_ = error_code_of(e);
+    error = "session_mgr: failed to load calendar from CSV content: " +
+            string(e.what());
+    cerr << error << endl;
+    return false;
+  }
+}
+
+optional<bool> session_mgr::_in_session_at(const string &product,
+                                           int64_t nanos_since_epoch,
+                                           bool include_begin,
+                                           bool include_end) const noexcept {
+  try {
+    return box_->in_session_at(product, nanos_since_epoch, include_begin,
+                               include_end);
+  } catch (const exception &e) {
+    last_error_ = error_code_of(e);
+    cerr << "session_mgr: in_session_at failed for product '" << product
+         << "': " << e.what() << endl;
+    return nullopt;
+  }
+}
+
+optional<bool>
+session_mgr::_any_in_session_between(const string &product, int64_t start,
+                                     int64_t end,
+                                     bool include_begin_end) const noexcept {
+  try {
+    return box_->any_in_session_between(product, start, end,
+                                        include_begin_end);
+  } catch (const exception &e) {
+    last_error_ = error_code_of(e);
+    cerr << "session_mgr: any_in_session_between failed for product '"
+         << product << "': " << e.what() << endl;
+    return nullopt;
+  }
+}
+
 } // namespace sessionrs
\ No newline at end of file
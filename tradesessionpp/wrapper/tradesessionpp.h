@@ -25,9 +25,18 @@ namespace sessionrs {
 
 using namespace std;
 
+// 错误代码, 参看tradesessionpp.rs中的ErrorCode, 异常信息以"[错误代码]"开头
+using error_code = sessionpp::ErrorCode;
+
+// 从异常中取得错误代码, 不是本库的异常时返回error_code::Unknown
+inline error_code error_code_of(const std::exception &e) noexcept {
+  return sessionpp::error_code_of(e.what());
+}
+
 class SESSIONRS_API tradesession {
 private:
   ::rust::Box<sessionpp::SessionPP> box_;
+  error_code last_error_ = error_code::Unknown;
 
 public:
   static tradesession new_full_session() noexcept {
@@ -64,6 +73,9 @@ public:
 
   void post_fix() noexcept { box_->post_fix(); }
 
+  // 最近一次失败的错误代码, 从未失败时为error_code::Unknown
+  error_code last_error() const noexcept { return last_error_; }
+
   // 该品种日线开始时间，9:00/9:15/9:30/21:00, 一般是集合竞价所在的时间
   template <typename _Duration = chrono::seconds>
   _Duration day_begin() const noexcept {
@@ -100,6 +112,26 @@ public:
                            include_begin_end);
   }
 
+  // 节假日, yyyymmdd格式的整数, 比如20251001, 为空时清除, 日期无效时返回false
+  bool set_holidays(const vector<int32_t> &holidays, string &error);
+
+  // 某个日期时间点落在session中吗? 排除周末, 以及set_holidays设置的节假日及节假日前的夜盘
+  // since_epoch是从1970-01-01 00:00:00开始的本地时间(不是UTC)
+  template <typename _Duration = chrono::seconds>
+  bool in_session_at(_Duration since_epoch, bool include_begin = true,
+                     bool include_end = false) const noexcept {
+    auto nanos = chrono::duration_cast<chrono::nanoseconds>(since_epoch);
+    return _in_session_at(nanos.count(), include_begin, include_end);
+  }
+  template <typename _Duration = chrono::seconds>
+  bool any_in_session_between(_Duration start, _Duration end,
+                              bool include_begin_end) const noexcept {
+    auto start_nanos = chrono::duration_cast<chrono::nanoseconds>(start);
+    auto end_nanos = chrono::duration_cast<chrono::nanoseconds>(end);
+    return _any_in_session_between(start_nanos.count(), end_nanos.count(),
+                                   include_begin_end);
+  }
+
 protected:
   // 避免template暴露box_对象,
   // tradessionpp.rs.h里面的方法在windows下面没有dllexport
@@ -116,29 +148,87 @@ protected:
                        bool include_begin_end) const noexcept {
     return box_->any_in_session(start, end, include_begin_end);
   }
+  bool _in_session_at(int64_t nanos_since_epoch, bool include_begin,
+                      bool include_end) const noexcept {
+    return box_->in_session_at(nanos_since_epoch, include_begin, include_end);
+  }
+  bool _any_in_session_between(int64_t start, int64_t end,
+                               bool include_begin_end) const noexcept {
+    return box_->any_in_session_between(start, end, include_begin_end);
+  }
 };
 
 class SESSIONRS_API session_mgr {
 private:
   ::rust::Box<sessionpp::SessionMgr> box_;
+  mutable error_code last_error_ = error_code::Unknown;
 
 public:
   session_mgr() : box_(sessionpp::new_mgr()) {};
 
   optional<tradesession> get_session(const string &product);
 
+  // 用合约代码获取session, 比如ag2412, SR501, m2501-C-3000
+  optional<tradesession> get_session_for_instrument(const string &instrument);
+
   bool load_from_csv(const string &csv_file_path, string &error,
                      bool merge = true);
 
+  // 宽松模式, 跳过有错误的行, row_errors为各行的错误信息(含行号及品种),
+  // 仅在读取失败时返回false
+  bool load_from_csv_lenient(const string &csv_file_path,
+                             vector<string> &row_errors, string &error,
+                             bool merge = true);
+
   bool load_from_csv_content(const string &csv_content, string &error,
                              bool merge = true);
 
+  // 节假日csv, 两列 exchange,date, exchange为`*`表示所有交易所通用
+  bool load_calendar_csv(const string &csv_file_path, string &error,
+                         bool merge = true);
+
+  bool load_calendar_csv_content(const string &csv_content, string &error,
+                                 bool merge = true);
+
+  // 排除周末、节假日及节假日前的夜盘, 品种不存在时返回nullopt
+  // since_epoch是从1970-01-01 00:00:00开始的本地时间(不是UTC)
+  template <typename _Duration = chrono::seconds>
+  optional<bool> in_session_at(const string &product, _Duration since_epoch,
+                               bool include_begin = true,
+                               bool include_end = false) const noexcept {
+    auto nanos = chrono::duration_cast<chrono::nanoseconds>(since_epoch);
+    return _in_session_at(product, nanos.count(), include_begin, include_end);
+  }
+
+  // start,end之间任意日期时间点落在session中吗? 排除周末、节假日及节假日前的夜盘,
+  // 品种不存在时返回nullopt
+  template <typename _Duration = chrono::seconds>
+  optional<bool> any_in_session_between(const string &product, _Duration start,
+                                        _Duration end,
+                                        bool include_begin_end) const noexcept {
+    auto start_nanos = chrono::duration_cast<chrono::nanoseconds>(start);
+    auto end_nanos = chrono::duration_cast<chrono::nanoseconds>(end);
+    return _any_in_session_between(product, start_nanos.count(),
+                                   end_nanos.count(), include_begin_end);
+  }
+
   size_t sessions_count() const { return box_->sessions_count(); }
 
+  // 最近一次失败的错误代码, 从未失败时为error_code::Unknown
+  error_code last_error() const noexcept { return last_error_; }
+
   // 这里没有做转换，直接返回原始的::rust类型
   ::rust::Vec<::rust::String> session_map_keys() {
     return box_->session_map_keys();
   }
+
+protected:
+  optional<bool> _in_session_at(const string &product,
+                                int64_t nanos_since_epoch, bool include_begin,
+                                bool include_end) const noexcept;
+  optional<bool> _any_in_session_between(const string &product, int64_t start,
+                                         int64_t end,
+                                         bool include_begin_end) const noexcept;
 };
 
 } // namespace sessionrs
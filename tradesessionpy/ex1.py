@@ -41,6 +41,12 @@ def test1():
         print(f"{k}:\n{v}\n")
     pass
 
+    # 加载节假日之后, 可以按完整的日期时间判断
+    mgr.reload_calendar_csv_file(str(file.parent / "holidays.csv"), merge=False)
+    # 春节前最后一个交易日晚上没有夜盘
+    assert mgr.in_session_at("ag", dt.datetime(2025, 1, 27, 14, 0), True) is True
+    assert mgr.in_session_at("ag", dt.datetime(2025, 1, 27, 21, 30), True) is False
+
     # 获取某个特定品种的session
     try:
         ru = mgr.get_session("ru")
@@ -1,15 +1,95 @@
-use anyhow::anyhow;
-use chrono::NaiveTime;
-use pyo3::exceptions::PyException;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use numpy::datetime::{Datetime, units::Nanoseconds};
+use numpy::{
+    PyArray1, PyArrayDescrMethods, PyArrayMethods, PyUntypedArray, PyUntypedArrayMethods, dtype,
+};
+use pyo3::exceptions::{PyException, PyTypeError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::{PyDateTime, PyTzInfo, PyTzInfoAccess};
 use pyo3_stub_gen::define_stub_info_gatherer;
 use pyo3_stub_gen::derive::{gen_stub_pyclass, gen_stub_pymethods};
 use std::collections::HashMap;
 
-use tradesession;
+pyo3_stub_gen::create_exception!(
+    tradesessionpy,
+    TradeSessionError,
+    PyException,
+    "所有错误的基类"
+);
 
-fn to_pyerr(err: anyhow::Error) -> PyErr {
-    PyErr::new::<PyException, _>(err.to_string())
+/// 定义TradeSessionError的子类, 并登记到pyi文件中
+macro_rules! create_error {
+    ($name: ident, $doc: expr) => {
+        pyo3::create_exception!(tradesessionpy, $name, TradeSessionError, $doc);
+
+        pyo3_stub_gen::inventory::submit! {
+            pyo3_stub_gen::type_info::PyErrorInfo {
+                name: stringify!($name),
+                module: "tradesessionpy",
+                base: || "TradeSessionError",
+            }
+        }
+    };
+}
+
+create_error!(InvalidSliceError, "时段不合法, 比如开始时间不早于结束时间");
+create_error!(OverlappingSliceError, "时段重叠");
+create_error!(InvalidAuctionError, "集合竞价时段不合法");
+create_error!(TimeFormatError, "时间或日期格式错误");
+create_error!(UnknownTimeZoneError, "时区名称无效");
+create_error!(JsonFormatError, "交易时段的json格式错误");
+create_error!(CsvFormatError, "csv文件格式错误");
+create_error!(UnknownProductError, "品种不存在");
+
+/// 文件读写错误转为python内置的OSError(比如FileNotFoundError), 其他错误转为TradeSessionError的子类
+fn to_pyerr(err: tradesession::TradeSessionError) -> PyErr {
+    use tradesession::TradeSessionError as E;
+    let msg = err.to_string();
+    match err {
+        E::InvalidSlice(_) => InvalidSliceError::new_err(msg),
+        E::OverlappingSlice(_) => OverlappingSliceError::new_err(msg),
+        E::InvalidAuction(_) => InvalidAuctionError::new_err(msg),
+        E::TimeFormat(_) => TimeFormatError::new_err(msg),
+        E::UnknownTimeZone(_) => UnknownTimeZoneError::new_err(msg),
+        E::JsonFormat(_) => JsonFormatError::new_err(msg),
+        E::CsvFormat { .. } | E::CsvRecord { .. } => CsvFormatError::new_err(msg),
+        E::UnknownProduct(_) => UnknownProductError::new_err(msg),
+        E::Io(err) => err.into(),
+        _ => TradeSessionError::new_err(msg),
+    }
+}
+
+fn unknown_product(product: &str) -> PyErr {
+    to_pyerr(tradesession::TradeSessionError::UnknownProduct(
+        product.to_string(),
+    ))
+}
+
+/// 带时区的datetime(tzinfo不能为None)转为时间点, 支持zoneinfo.ZoneInfo及datetime.timezone
+fn to_zoned(dt: &Bound<'_, PyDateTime>) -> PyResult<tradesession::jcswitch::MyZonedType> {
+    if dt.get_tzinfo().is_none() {
+        return Err(PyTypeError::new_err(
+            "expected a datetime with non-None tzinfo",
+        ));
+    }
+    let utc = dt
+        .call_method1("astimezone", (PyTzInfo::utc(dt.py())?,))?
+        .extract::<chrono::DateTime<Utc>>()?;
+    let nanos = utc
+        .timestamp_nanos_opt()
+        .ok_or_else(|| PyValueError::new_err("datetime out of range"))?;
+    let tz = tradesession::jcswitch::parse_time_zone("UTC").map_err(to_pyerr)?;
+    Ok(tradesession::jcswitch::zoned_from_timestamp_nanos(
+        nanos, &tz,
+    ))
+}
+
+fn to_bar_align(align_clock: bool) -> tradesession::BarAlign {
+    if align_clock {
+        tradesession::BarAlign::Clock
+    } else {
+        tradesession::BarAlign::SessionStart
+    }
 }
 
 #[gen_stub_pyclass]
@@ -67,20 +147,59 @@ impl TradeSession {
         let session = tradesession::TradeSession::new_from_minutes(minutes);
         Ok(Self { session })
     }
+    /// 以anchor作为交易日开始时间的空时段, 比如CME Globex的17:00, 之后用add_slice添加时段
+    #[staticmethod]
+    pub fn new_with_anchor(anchor: NaiveTime) -> Self {
+        let session = tradesession::TradeSession::new_with_anchor(&anchor);
+        Self { session }
+    }
+
+    /// 交易日开始时间, 国内默认20:00
+    pub fn anchor(&self) -> NaiveTime {
+        self.session.anchor()
+    }
+
+    /// 设置时区, IANA时区名, 比如"America/Chicago", 时区名错误时会爆出异常
+    pub fn set_time_zone(&mut self, name: &str) -> PyResult<()> {
+        let tz = tradesession::jcswitch::parse_time_zone(name).map_err(to_pyerr)?;
+        self.session.set_time_zone(&tz);
+        Ok(())
+    }
+
+    /// IANA时区名, 没有设置时返回None
+    pub fn time_zone(&self) -> Option<String> {
+        self.session
+            .time_zone()
+            .map(tradesession::jcswitch::time_zone_name)
+    }
+
+    /// 带时区的datetime, 按本session时区的当地时间可以交易吗? 排除周末,
+    /// holidays为节假日(datetime.date的列表), 不为None时也排除节假日及节假日前的夜盘
+    #[pyo3(signature = (dt, holidays=None))]
+    pub fn is_tradable_zoned(
+        &self,
+        dt: &Bound<'_, PyDateTime>,
+        holidays: Option<Vec<NaiveDate>>,
+    ) -> PyResult<bool> {
+        let calendar = holidays.map(tradesession::TradingCalendar::new_from_holidays);
+        Ok(self
+            .session
+            .is_tradable_zoned(&to_zoned(dt)?, calendar.as_ref()))
+    }
 
     /// 该品种日线开始时间，9:00/9:15/9:30/21:00, 一般是集合竞价所在的时间
     pub fn day_begin(&self) -> NaiveTime {
-        self.session.day_begin().clone()
+        *self.session.day_begin()
     }
 
     ///该品种日线结束时间，商品15:00，股指曾经15:15，股指现在15:00
     pub fn day_end(&self) -> NaiveTime {
-        self.session.day_end().clone()
+        *self.session.day_end()
     }
 
     /// 该品种早盘开始时间，9:00/9:15/9:30,非夜盘品种跟day_begin相同
     pub fn morning_begin(&self) -> NaiveTime {
-        self.session.morning_begin().clone()
+        *self.session.morning_begin()
     }
 
     /// 是否有夜盘交易
@@ -92,6 +211,38 @@ impl TradeSession {
     pub fn in_session(&self, ts: NaiveTime, include_begin: bool, include_end: bool) -> bool {
         self.session.in_session(&ts, include_begin, include_end)
     }
+    /// 批量判断, ts为NumPy的datetime64[ns]数组(不带时区)或int64数组(自零点起的纳秒数或epoch纳秒数),
+    /// 返回同样长度的bool数组, 计算时释放GIL
+    #[pyo3(signature = (ts, include_begin, include_end=false))]
+    pub fn in_session_batch<'py>(
+        &self,
+        py: Python<'py>,
+        ts: &Bound<'py, PyUntypedArray>,
+        include_begin: bool,
+        include_end: bool,
+    ) -> PyResult<Bound<'py, PyUntypedArray>> {
+        let ts = if ts.dtype().is_equiv_to(&dtype::<Datetime<Nanoseconds>>(py)) {
+            ts.call_method1("view", ("int64",))?
+        } else {
+            ts.clone().into_any()
+        };
+        let ts = ts
+            .downcast::<PyArray1<i64>>()
+            .map_err(|_| PyTypeError::new_err("expected a 1-D datetime64[ns] or int64 array"))?;
+        let ts = ts.readonly();
+        let batch = |ts: &[i64]| {
+            py.allow_threads(|| {
+                self.session
+                    .in_session_batch(ts, include_begin, include_end)
+            })
+        };
+        let result = match ts.as_slice() {
+            Ok(slice) => batch(slice),
+            // 不连续的数组(比如切片arr[::2])先复制
+            Err(_) => batch(&ts.as_array().iter().copied().collect::<Vec<_>>()),
+        };
+        Ok(PyArray1::from_vec(py, result).as_untyped().clone())
+    }
     /// start, end之间任意时间点落在session中吗?
     pub fn any_in_session(
         &self,
@@ -102,6 +253,67 @@ impl TradeSession {
         self.session.any_in_session(&start, &end, include_begin_end)
     }
 
+    /// 一个日期时间点, 在时段内吗? 排除周末及周末前后的夜盘,
+    /// holidays为节假日(datetime.date的列表), 不为None时也排除节假日及节假日前的夜盘,
+    /// 需要反复查询时请使用SessionMgr.in_session_at
+    #[pyo3(signature = (dt, include_begin, include_end=false, holidays=None))]
+    pub fn in_session_at(
+        &self,
+        dt: NaiveDateTime,
+        include_begin: bool,
+        include_end: bool,
+        holidays: Option<Vec<NaiveDate>>,
+    ) -> bool {
+        let calendar = holidays.map(tradesession::TradingCalendar::new_from_holidays);
+        self.session
+            .in_session_at(&dt, include_begin, include_end, calendar.as_ref())
+    }
+    /// start, end之间任意日期时间点落在session中吗? 排除周末, holidays参看in_session_at
+    #[pyo3(signature = (start, end, include_begin_end, holidays=None))]
+    pub fn any_in_session_between(
+        &self,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+        include_begin_end: bool,
+        holidays: Option<Vec<NaiveDate>>,
+    ) -> bool {
+        let calendar = holidays.map(tradesession::TradingCalendar::new_from_holidays);
+        self.session
+            .any_in_session_between(&start, &end, include_begin_end, calendar.as_ref())
+    }
+    /// 一个时间点所处的交易阶段, 返回PreOpenAuction/AuctionMatch/PreOpenQueue/Continuous/Break/ClosingAuction/Closed
+    pub fn phase_at(&self, ts: NaiveTime) -> &'static str {
+        self.session.phase_at(&ts).as_str()
+    }
+
+    /// 一个交易日的所有1分钟bar的结束时间, 按交易时间先后排序, 夜盘在前
+    pub fn minute_bar_ends(&self) -> Vec<NaiveTime> {
+        self.session.minute_bar_ends()
+    }
+
+    /// 一个交易日的所有period分钟bar的结束时间, align_clock为false时从夜盘及日盘开盘起累计交易分钟数,
+    /// bar可以跨越盘中休息, 为true时按时钟整点对齐, bar不跨越盘中休息
+    #[pyo3(signature = (period, align_clock=false))]
+    pub fn bar_ends(&self, period: u32, align_clock: bool) -> Vec<NaiveTime> {
+        self.session
+            .bar_boundaries(period, to_bar_align(align_clock), false)
+            .iter()
+            .map(|bar| bar.end_time())
+            .collect()
+    }
+    /// 一个时间点属于交易日的第几根period分钟bar(从0开始, 夜盘在前), 不在任何bar内时返回None
+    #[pyo3(signature = (ts, period, align_clock=false))]
+    pub fn bar_index(&self, ts: NaiveTime, period: u32, align_clock: bool) -> Option<u32> {
+        self.session
+            .bar_index(&ts, period, to_bar_align(align_clock))
+    }
+    /// bar_index的逆运算, 第index根period分钟bar的结束时间, 超出范围时返回None
+    #[pyo3(signature = (index, period, align_clock=false))]
+    pub fn bar_end_time(&self, index: u32, period: u32, align_clock: bool) -> Option<NaiveTime> {
+        self.session
+            .bar_end_time(index, period, to_bar_align(align_clock))
+    }
+
     pub fn minutes_list(&self) -> Vec<u16> {
         self.session.minutes_list().iter().cloned().collect()
     }
@@ -118,10 +330,39 @@ impl TradeSession {
             .map_err(to_pyerr)
     }
 
-    /// 在所有Slice都加入之后，使用minutes方式重算，合并并移除重叠等，计算day_begin、day_end的值
+    /// 并集, 不含集合竞价时段, 结果使用self的anchor及时区
+    pub fn union(&self, other: &TradeSession) -> TradeSession {
+        let session = self.session.union(&other.session);
+        Self { session }
+    }
+    /// 交集, 不含集合竞价时段, 结果使用self的anchor及时区
+    pub fn intersection(&self, other: &TradeSession) -> TradeSession {
+        let session = self.session.intersection(&other.session);
+        Self { session }
+    }
+    /// 差集, self开市而other休市的时段
+    pub fn difference(&self, other: &TradeSession) -> TradeSession {
+        let session = self.session.difference(&other.session);
+        Self { session }
+    }
+    /// self的所有时段是否都在other之内
+    pub fn is_subset_of(&self, other: &TradeSession) -> bool {
+        self.session.is_subset_of(&other.session)
+    }
+
+    /// 在所有Slice都加入之后，按秒排序，合并相连或重叠的时段，计算day_begin、day_end的值, 不是整分钟的开始结束时间(比如11:29:30)保持不变, anchor不同的slice会按本session的anchor重新平移
     pub fn post_fix(&mut self) {
         self.session.post_fix();
     }
+    /// 检查可疑的定义, 返回各问题的描述, 已经post_fix的session无法报告重叠的时段
+    pub fn validate(&self) -> Vec<String> {
+        self.session
+            .validate()
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect()
+    }
+    #[allow(clippy::inherent_to_string)]
     #[pyo3(name = "__str__")]
     pub fn to_string(&self) -> String {
         format!("{}", self.session)
@@ -165,6 +406,40 @@ impl SessionMgr {
             .reload_csv_file(csv_file_path, merge)
             .map_err(to_pyerr)
     }
+    /// 宽松模式, 跳过有错误的行, 返回各行的错误信息(含行号及品种), 仅在读取失败时爆出异常
+    pub fn reload_csv_content_lenient(
+        &mut self,
+        csv_content: &str,
+        merge: bool,
+    ) -> PyResult<Vec<String>> {
+        self.mgr
+            .reload_csv_content_lenient(csv_content, merge)
+            .map(|errors| errors.iter().map(|e| e.to_string()).collect())
+            .map_err(to_pyerr)
+    }
+    /// 宽松模式, 跳过有错误的行, 返回各行的错误信息(含行号及品种), 仅在读取失败时爆出异常
+    pub fn reload_csv_file_lenient(
+        &mut self,
+        csv_file_path: &str,
+        merge: bool,
+    ) -> PyResult<Vec<String>> {
+        self.mgr
+            .reload_csv_file_lenient(csv_file_path, merge)
+            .map(|errors| errors.iter().map(|e| e.to_string()).collect())
+            .map_err(to_pyerr)
+    }
+    /// 节假日csv, 两列 exchange,date, exchange为`*`表示所有交易所通用
+    pub fn reload_calendar_csv_content(&mut self, csv_content: &str, merge: bool) -> PyResult<()> {
+        self.mgr
+            .reload_calendar_csv_content(csv_content, merge)
+            .map_err(to_pyerr)
+    }
+    /// 节假日csv, 两列 exchange,date, exchange为`*`表示所有交易所通用
+    pub fn reload_calendar_csv_file(&mut self, csv_file_path: &str, merge: bool) -> PyResult<()> {
+        self.mgr
+            .reload_calendar_csv_file(csv_file_path, merge)
+            .map_err(to_pyerr)
+    }
     pub fn has_session(&self, product: &str) -> bool {
         self.mgr.has_session(product)
     }
@@ -172,9 +447,39 @@ impl SessionMgr {
     /// 获取失败时会爆出异常
     pub fn get_session(&self, product: &str) -> PyResult<TradeSession> {
         self.mgr
-            .get_session(product)
+            .try_get_session(product)
             .map(|s| TradeSession { session: s.clone() })
-            .ok_or_else(|| to_pyerr(anyhow!("Session for product '{}' not found", product)))
+            .map_err(to_pyerr)
+    }
+    /// 用合约代码获取session, 比如ag2412, SR501, m2501-C-3000, 获取失败时会爆出异常
+    pub fn get_session_for_instrument(&self, instrument: &str) -> PyResult<TradeSession> {
+        self.mgr
+            .try_get_session_for_instrument(instrument)
+            .map(|s| TradeSession { session: s.clone() })
+            .map_err(to_pyerr)
+    }
+    /// 合约代码对应的品种代码, 找不到时返回None
+    pub fn resolve_product(&self, instrument: &str) -> Option<String> {
+        self.mgr.resolve_product(instrument).map(|s| s.to_string())
+    }
+    /// 用(交易所, 品种)获取session, 获取失败时会爆出异常
+    pub fn get_session_on_exchange(&self, exchange: &str, product: &str) -> PyResult<TradeSession> {
+        self.mgr
+            .get_session_on_exchange(exchange, product)
+            .map(|s| TradeSession { session: s.clone() })
+            .ok_or_else(|| unknown_product(&format!("{}.{}", exchange, product)))
+    }
+    /// 品种所属的交易所, 品种不存在或者交易所未知时返回None
+    pub fn exchange_of(&self, product: &str) -> Option<String> {
+        self.mgr.exchange_of(product).map(|s| s.to_string())
+    }
+    /// 该交易所的所有品种, 按品种代码排序
+    pub fn products_on(&self, exchange: &str) -> Vec<String> {
+        self.mgr
+            .products_on(exchange)
+            .into_iter()
+            .map(|s| s.to_string())
+            .collect()
     }
     /// 如果旧数据存在，会被替换
     pub fn add_session(&mut self, product: &str, session: &TradeSession) {
@@ -184,22 +489,22 @@ impl SessionMgr {
     pub fn day_begin(&self, product: &str) -> PyResult<NaiveTime> {
         self.mgr
             .day_begin(product)
-            .map(|tm| *tm)
-            .ok_or_else(|| to_pyerr(anyhow!("day begin for product '{}' not found", product)))
+            .copied()
+            .ok_or_else(|| unknown_product(product))
     }
     /// 获取失败时会爆出异常
     pub fn day_end(&self, product: &str) -> PyResult<NaiveTime> {
         self.mgr
             .day_end(product)
-            .map(|tm| *tm)
-            .ok_or_else(|| to_pyerr(anyhow!("day end for product '{}' not found", product)))
+            .copied()
+            .ok_or_else(|| unknown_product(product))
     }
     /// 获取失败时会爆出异常
     pub fn morning_begin(&self, product: &str) -> PyResult<NaiveTime> {
         self.mgr
             .morning_begin(product)
-            .map(|tm| *tm)
-            .ok_or_else(|| to_pyerr(anyhow!("morning_begin for product '{}' not found", product)))
+            .copied()
+            .ok_or_else(|| unknown_product(product))
     }
     /// 获取失败时会爆出异常
     #[pyo3(signature = (product, ts, include_begin, include_end=false))]
@@ -213,7 +518,7 @@ impl SessionMgr {
         let opt = self
             .mgr
             .in_session(product, &ts, include_begin, include_end);
-        opt.ok_or_else(|| to_pyerr(anyhow!("Session for product '{}' not found", product)))
+        opt.ok_or_else(|| unknown_product(product))
     }
 
     /// start, end之间任意时间点落在session中吗?
@@ -227,7 +532,137 @@ impl SessionMgr {
         let opt = self
             .mgr
             .any_in_session(product, &start, &end, include_begin_end);
-        opt.ok_or_else(|| to_pyerr(anyhow!("Session for product '{}' not found", product)))
+        opt.ok_or_else(|| unknown_product(product))
+    }
+    /// 一个日期时间点所处的交易阶段, 休市的日期为Closed, 获取失败时会爆出异常,
+    /// 返回PreOpenAuction/AuctionMatch/PreOpenQueue/Continuous/Break/ClosingAuction/Closed
+    pub fn phase_at(&self, product: &str, dt: NaiveDateTime) -> PyResult<&'static str> {
+        self.mgr
+            .phase_at_datetime(product, &dt)
+            .map(|phase| phase.as_str())
+            .ok_or_else(|| unknown_product(product))
+    }
+
+    /// 一个日期时间点, 在时段内吗? 排除周末、节假日及节假日前的夜盘, 获取失败时会爆出异常
+    #[pyo3(signature = (product, dt, include_begin, include_end=false))]
+    pub fn in_session_at(
+        &self,
+        product: &str,
+        dt: NaiveDateTime,
+        include_begin: bool,
+        include_end: bool,
+    ) -> PyResult<bool> {
+        let opt = self
+            .mgr
+            .in_session_at(product, &dt, include_begin, include_end);
+        opt.ok_or_else(|| unknown_product(product))
+    }
+
+    /// start, end之间任意日期时间点落在session中吗? 排除周末、节假日及节假日前的夜盘
+    pub fn any_in_session_between(
+        &self,
+        product: &str,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+        include_begin_end: bool,
+    ) -> PyResult<bool> {
+        let opt = self
+            .mgr
+            .any_in_session_between(product, &start, &end, include_begin_end);
+        opt.ok_or_else(|| unknown_product(product))
+    }
+    /// 设置交易所的时区, IANA时区名, 比如"America/Chicago", 时区名错误时会爆出异常
+    pub fn set_time_zone(&mut self, exchange: &str, name: &str) -> PyResult<()> {
+        let tz = tradesession::jcswitch::parse_time_zone(name).map_err(to_pyerr)?;
+        self.mgr.set_time_zone(exchange, &tz);
+        Ok(())
+    }
+
+    /// 带时区的datetime, 按品种所属交易所的时区及日历可以交易吗? 获取失败时会爆出异常
+    pub fn is_tradable_zoned(&self, product: &str, dt: &Bound<'_, PyDateTime>) -> PyResult<bool> {
+        let opt = self.mgr.is_tradable_zoned(product, &to_zoned(dt)?);
+        opt.ok_or_else(|| unknown_product(product))
+    }
+
+    /// 带时区的datetime所处的交易阶段, 参看phase_at, 获取失败时会爆出异常
+    pub fn phase_at_zoned(
+        &self,
+        product: &str,
+        dt: &Bound<'_, PyDateTime>,
+    ) -> PyResult<&'static str> {
+        self.mgr
+            .phase_at_zoned(product, &to_zoned(dt)?)
+            .map(|phase| phase.as_str())
+            .ok_or_else(|| unknown_product(product))
+    }
+    /// 多个品种的时段的并集, 任一品种不存在时会爆出异常
+    pub fn union_of(&self, products: Vec<String>) -> PyResult<TradeSession> {
+        let products: Vec<&str> = products.iter().map(|s| s.as_str()).collect();
+        self.mgr
+            .union_of(&products)
+            .map(|session| TradeSession { session })
+            .ok_or_else(|| unknown_product(&products.join(", ")))
+    }
+    /// 多个品种的时段的交集, 任一品种不存在时会爆出异常
+    pub fn intersection_of(&self, products: Vec<String>) -> PyResult<TradeSession> {
+        let products: Vec<&str> = products.iter().map(|s| s.as_str()).collect();
+        self.mgr
+            .intersection_of(&products)
+            .map(|session| TradeSession { session })
+            .ok_or_else(|| unknown_product(&products.join(", ")))
+    }
+    /// 价差合约两条腿的共同交易时段, 任一品种不存在时会爆出异常
+    pub fn common_session(&self, leg_a: &str, leg_b: &str) -> PyResult<TradeSession> {
+        self.mgr
+            .common_session(leg_a, leg_b)
+            .map(|common| TradeSession {
+                session: common.session().clone(),
+            })
+            .ok_or_else(|| unknown_product(&format!("{} or {}", leg_a, leg_b)))
+    }
+    /// 只有一条腿开市的时段, (开始时间, 结束时间, 休市的腿, 原因), 任一品种不存在时会爆出异常
+    pub fn spread_gaps(
+        &self,
+        leg_a: &str,
+        leg_b: &str,
+    ) -> PyResult<Vec<(NaiveTime, NaiveTime, String, String)>> {
+        let common = self
+            .mgr
+            .common_session(leg_a, leg_b)
+            .ok_or_else(|| unknown_product(&format!("{} or {}", leg_a, leg_b)))?;
+        Ok(common
+            .gaps()
+            .iter()
+            .map(|gap| {
+                (
+                    gap.begin_time(),
+                    gap.end_time(),
+                    gap.closed_leg().to_string(),
+                    gap.to_string(),
+                )
+            })
+            .collect())
+    }
+    /// 一个日期时间点, 价差合约的两条腿都可以交易吗? 任一品种不存在时会爆出异常
+    pub fn spread_tradable(&self, leg_a: &str, leg_b: &str, dt: NaiveDateTime) -> PyResult<bool> {
+        self.mgr
+            .spread_tradable(leg_a, leg_b, &dt)
+            .ok_or_else(|| unknown_product(&format!("{} or {}", leg_a, leg_b)))
+    }
+    /// 检查所有品种的所有版本, 返回各问题的描述, 加载时重叠的时段已被合并, 需要时请用lint_csv
+    pub fn lint(&self) -> Vec<String> {
+        self.mgr
+            .lint()
+            .iter()
+            .map(|lint| lint.to_string())
+            .collect()
+    }
+    /// 检查csv文件的每一行, 返回各问题的描述(含行号及品种), 仅在读取失败时爆出异常
+    #[staticmethod]
+    pub fn lint_csv(csv_file_path: &str) -> PyResult<Vec<String>> {
+        tradesession::lint_csv(csv_file_path)
+            .map(|lints| lints.iter().map(|lint| lint.to_string()).collect())
+            .map_err(to_pyerr)
     }
     #[getter]
     pub fn sessions_count(&self) -> usize {
@@ -243,11 +678,33 @@ impl SessionMgr {
     }
 }
 
+impl Default for SessionMgr {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// A Python module implemented in Rust.
 #[pymodule]
 fn tradesessionpy(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<TradeSession>()?;
     m.add_class::<SessionMgr>()?;
+    let py = m.py();
+    m.add("TradeSessionError", py.get_type::<TradeSessionError>())?;
+    m.add("InvalidSliceError", py.get_type::<InvalidSliceError>())?;
+    m.add(
+        "OverlappingSliceError",
+        py.get_type::<OverlappingSliceError>(),
+    )?;
+    m.add("InvalidAuctionError", py.get_type::<InvalidAuctionError>())?;
+    m.add("TimeFormatError", py.get_type::<TimeFormatError>())?;
+    m.add(
+        "UnknownTimeZoneError",
+        py.get_type::<UnknownTimeZoneError>(),
+    )?;
+    m.add("JsonFormatError", py.get_type::<JsonFormatError>())?;
+    m.add("CsvFormatError", py.get_type::<CsvFormatError>())?;
+    m.add("UnknownProductError", py.get_type::<UnknownProductError>())?;
     Ok(())
 }
 
@@ -3,6 +3,7 @@
 
 import builtins
 import datetime
+import numpy.typing
 import typing
 
 class SessionMgr:
@@ -28,11 +29,47 @@ class SessionMgr:
         注意sessions列,(json里面有逗号,需要多重双引号)
         ag,SHFE,"[{""Begin"":""09:00:00"",""End"":""10:15:00""},{""Begin"":""10:30:00"",""End"":""11:30:00""},{""Begin"":""13:30:00"",""End"":""15:00:00""},{""Begin"":""21:00:00"",""End"":""02:30:00""}]"
         """
+    def reload_csv_content_lenient(self, csv_content:builtins.str, merge:builtins.bool) -> builtins.list[builtins.str]:
+        r"""
+        宽松模式, 跳过有错误的行, 返回各行的错误信息(含行号及品种), 仅在读取失败时爆出异常
+        """
+    def reload_csv_file_lenient(self, csv_file_path:builtins.str, merge:builtins.bool) -> builtins.list[builtins.str]:
+        r"""
+        宽松模式, 跳过有错误的行, 返回各行的错误信息(含行号及品种), 仅在读取失败时爆出异常
+        """
+    def reload_calendar_csv_content(self, csv_content:builtins.str, merge:builtins.bool) -> None:
+        r"""
+        节假日csv, 两列 exchange,date, exchange为`*`表示所有交易所通用
+        """
+    def reload_calendar_csv_file(self, csv_file_path:builtins.str, merge:builtins.bool) -> None:
+        r"""
+        节假日csv, 两列 exchange,date, exchange为`*`表示所有交易所通用
+        """
     def has_session(self, product:builtins.str) -> builtins.bool: ...
     def get_session(self, product:builtins.str) -> TradeSession:
         r"""
         获取失败时会爆出异常
         """
+    def get_session_for_instrument(self, instrument:builtins.str) -> TradeSession:
+        r"""
+        用合约代码获取session, 比如ag2412, SR501, m2501-C-3000, 获取失败时会爆出异常
+        """
+    def resolve_product(self, instrument:builtins.str) -> typing.Optional[builtins.str]:
+        r"""
+        合约代码对应的品种代码, 找不到时返回None
+        """
+    def get_session_on_exchange(self, exchange:builtins.str, product:builtins.str) -> TradeSession:
+        r"""
+        用(交易所, 品种)获取session, 获取失败时会爆出异常
+        """
+    def exchange_of(self, product:builtins.str) -> typing.Optional[builtins.str]:
+        r"""
+        品种所属的交易所, 品种不存在或者交易所未知时返回None
+        """
+    def products_on(self, exchange:builtins.str) -> builtins.list[builtins.str]:
+        r"""
+        该交易所的所有品种, 按品种代码排序
+        """
     def add_session(self, product:builtins.str, session:TradeSession) -> None:
         r"""
         如果旧数据存在，会被替换
@@ -57,6 +94,60 @@ class SessionMgr:
         r"""
         start, end之间任意时间点落在session中吗?
         """
+    def phase_at(self, product:builtins.str, dt:datetime.datetime) -> builtins.str:
+        r"""
+        一个日期时间点所处的交易阶段, 休市的日期为Closed, 获取失败时会爆出异常,
+        返回PreOpenAuction/AuctionMatch/PreOpenQueue/Continuous/Break/ClosingAuction/Closed
+        """
+    def in_session_at(self, product:builtins.str, dt:datetime.datetime, include_begin:builtins.bool, include_end:builtins.bool=False) -> builtins.bool:
+        r"""
+        一个日期时间点, 在时段内吗? 排除周末、节假日及节假日前的夜盘, 获取失败时会爆出异常
+        """
+    def any_in_session_between(self, product:builtins.str, start:datetime.datetime, end:datetime.datetime, include_begin_end:builtins.bool) -> builtins.bool:
+        r"""
+        start, end之间任意日期时间点落在session中吗? 排除周末、节假日及节假日前的夜盘
+        """
+    def set_time_zone(self, exchange:builtins.str, name:builtins.str) -> None:
+        r"""
+        设置交易所的时区, IANA时区名, 比如"America/Chicago", 时区名错误时会爆出异常
+        """
+    def is_tradable_zoned(self, product:builtins.str, dt:datetime.datetime) -> builtins.bool:
+        r"""
+        带时区的datetime, 按品种所属交易所的时区及日历可以交易吗? 获取失败时会爆出异常
+        """
+    def phase_at_zoned(self, product:builtins.str, dt:datetime.datetime) -> builtins.str:
+        r"""
+        带时区的datetime所处的交易阶段, 参看phase_at, 获取失败时会爆出异常
+        """
+    def union_of(self, products:typing.Sequence[builtins.str]) -> TradeSession:
+        r"""
+        多个品种的时段的并集, 任一品种不存在时会爆出异常
+        """
+    def intersection_of(self, products:typing.Sequence[builtins.str]) -> TradeSession:
+        r"""
+        多个品种的时段的交集, 任一品种不存在时会爆出异常
+        """
+    def common_session(self, leg_a:builtins.str, leg_b:builtins.str) -> TradeSession:
+        r"""
+        价差合约两条腿的共同交易时段, 任一品种不存在时会爆出异常
+        """
+    def spread_gaps(self, leg_a:builtins.str, leg_b:builtins.str) -> builtins.list[tuple[datetime.time, datetime.time, builtins.str, builtins.str]]:
+        r"""
+        只有一条腿开市的时段, (开始时间, 结束时间, 休市的腿, 原因), 任一品种不存在时会爆出异常
+        """
+    def spread_tradable(self, leg_a:builtins.str, leg_b:builtins.str, dt:datetime.datetime) -> builtins.bool:
+        r"""
+        一个日期时间点, 价差合约的两条腿都可以交易吗? 任一品种不存在时会爆出异常
+        """
+    def lint(self) -> builtins.list[builtins.str]:
+        r"""
+        检查所有品种的所有版本, 返回各问题的描述, 加载时重叠的时段已被合并, 需要时请用lint_csv
+        """
+    @staticmethod
+    def lint_csv(csv_file_path:builtins.str) -> builtins.list[builtins.str]:
+        r"""
+        检查csv文件的每一行, 返回各问题的描述(含行号及品种), 仅在读取失败时爆出异常
+        """
     def session_map(self) -> builtins.dict[builtins.str, TradeSession]: ...
 
 class TradeSession:
@@ -85,6 +176,28 @@ class TradeSession:
         生成一个涵盖商品股指国债股票等的全部交易时段(含夜盘)
         """
     def __new__(cls, minutes:typing.Sequence[builtins.int]) -> TradeSession: ...
+    @staticmethod
+    def new_with_anchor(anchor:datetime.time) -> TradeSession:
+        r"""
+        以anchor作为交易日开始时间的空时段, 比如CME Globex的17:00, 之后用add_slice添加时段
+        """
+    def anchor(self) -> datetime.time:
+        r"""
+        交易日开始时间, 国内默认20:00
+        """
+    def set_time_zone(self, name:builtins.str) -> None:
+        r"""
+        设置时区, IANA时区名, 比如"America/Chicago", 时区名错误时会爆出异常
+        """
+    def time_zone(self) -> typing.Optional[builtins.str]:
+        r"""
+        IANA时区名, 没有设置时返回None
+        """
+    def is_tradable_zoned(self, dt:datetime.datetime, holidays:typing.Optional[typing.Sequence[datetime.date]]=None) -> builtins.bool:
+        r"""
+        带时区的datetime, 按本session时区的当地时间可以交易吗? 排除周末,
+        holidays为节假日(datetime.date的列表), 不为None时也排除节假日及节假日前的夜盘
+        """
     def day_begin(self) -> datetime.time:
         r"""
         该品种日线开始时间，9:00/9:15/9:30/21:00, 一般是集合竞价所在的时间
@@ -102,15 +215,89 @@ class TradeSession:
         是否有夜盘交易
         """
     def in_session(self, ts:datetime.time, include_begin:builtins.bool, include_end:builtins.bool=False) -> builtins.bool: ...
+    def in_session_batch(self, ts:numpy.typing.NDArray[typing.Any], include_begin:builtins.bool, include_end:builtins.bool=False) -> numpy.typing.NDArray[typing.Any]:
+        r"""
+        批量判断, ts为NumPy的datetime64[ns]数组(不带时区)或int64数组(自零点起的纳秒数或epoch纳秒数),
+        返回同样长度的bool数组, 计算时释放GIL
+        """
     def any_in_session(self, start:datetime.time, end:datetime.time, include_begin_end:builtins.bool) -> builtins.bool:
         r"""
         start, end之间任意时间点落在session中吗?
         """
+    def in_session_at(self, dt:datetime.datetime, include_begin:builtins.bool, include_end:builtins.bool=False, holidays:typing.Optional[typing.Sequence[datetime.date]]=None) -> builtins.bool:
+        r"""
+        一个日期时间点, 在时段内吗? 排除周末及周末前后的夜盘,
+        holidays为节假日(datetime.date的列表), 不为None时也排除节假日及节假日前的夜盘,
+        需要反复查询时请使用SessionMgr.in_session_at
+        """
+    def any_in_session_between(self, start:datetime.datetime, end:datetime.datetime, include_begin_end:builtins.bool, holidays:typing.Optional[typing.Sequence[datetime.date]]=None) -> builtins.bool:
+        r"""
+        start, end之间任意日期时间点落在session中吗? 排除周末, holidays参看in_session_at
+        """
+    def phase_at(self, ts:datetime.time) -> builtins.str:
+        r"""
+        一个时间点所处的交易阶段, 返回PreOpenAuction/AuctionMatch/PreOpenQueue/Continuous/Break/ClosingAuction/Closed
+        """
+    def minute_bar_ends(self) -> builtins.list[datetime.time]:
+        r"""
+        一个交易日的所有1分钟bar的结束时间, 按交易时间先后排序, 夜盘在前
+        """
+    def bar_ends(self, period:builtins.int, align_clock:builtins.bool=False) -> builtins.list[datetime.time]:
+        r"""
+        一个交易日的所有period分钟bar的结束时间, align_clock为false时从夜盘及日盘开盘起累计交易分钟数,
+        bar可以跨越盘中休息, 为true时按时钟整点对齐, bar不跨越盘中休息
+        """
+    def bar_index(self, ts:datetime.time, period:builtins.int, align_clock:builtins.bool=False) -> typing.Optional[builtins.int]:
+        r"""
+        一个时间点属于交易日的第几根period分钟bar(从0开始, 夜盘在前), 不在任何bar内时返回None
+        """
+    def bar_end_time(self, index:builtins.int, period:builtins.int, align_clock:builtins.bool=False) -> typing.Optional[datetime.time]:
+        r"""
+        bar_index的逆运算, 第index根period分钟bar的结束时间, 超出范围时返回None
+        """
     def minutes_list(self) -> builtins.list[builtins.int]: ...
     def add_slice(self, start_hour:builtins.int, start_minute:builtins.int, end_hour:builtins.int, end_minute:builtins.int) -> None: ...
+    def union(self, other:TradeSession) -> TradeSession:
+        r"""
+        并集, 不含集合竞价时段, 结果使用self的anchor及时区
+        """
+    def intersection(self, other:TradeSession) -> TradeSession:
+        r"""
+        交集, 不含集合竞价时段, 结果使用self的anchor及时区
+        """
+    def difference(self, other:TradeSession) -> TradeSession:
+        r"""
+        差集, self开市而other休市的时段
+        """
+    def is_subset_of(self, other:TradeSession) -> builtins.bool:
+        r"""
+        self的所有时段是否都在other之内
+        """
     def post_fix(self) -> None:
         r"""
-        在所有Slice都加入之后，使用minutes方式重算，合并并移除重叠等，计算day_begin、day_end的值
+        在所有Slice都加入之后，按秒排序，合并相连或重叠的时段，计算day_begin、day_end的值, 不是整分钟的开始结束时间(比如11:29:30)保持不变, anchor不同的slice会按本session的anchor重新平移
+        """
+    def validate(self) -> builtins.list[builtins.str]:
+        r"""
+        检查可疑的定义, 返回各问题的描述, 已经post_fix的session无法报告重叠的时段
         """
     def __str__(self) -> builtins.str: ...
 
+class CsvFormatError(TradeSessionError): ...
+
+class InvalidAuctionError(TradeSessionError): ...
+
+class InvalidSliceError(TradeSessionError): ...
+
+class JsonFormatError(TradeSessionError): ...
+
+class OverlappingSliceError(TradeSessionError): ...
+
+class TimeFormatError(TradeSessionError): ...
+
+class TradeSessionError(Exception): ...
+
+class UnknownProductError(TradeSessionError): ...
+
+class UnknownTimeZoneError(TradeSessionError): ...
+